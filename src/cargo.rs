@@ -0,0 +1,310 @@
+use std::path::{Component, Path, PathBuf};
+use toml::Value;
+
+#[derive(Debug)]
+pub struct CargoDependency {
+    /// Name of the package, which differs from the key of renamed dependencies.
+    pub name: String,
+    /// Key in the dependency table, the name features refer to.
+    pub key: String,
+    pub path: Option<PathBuf>,
+    pub optional: bool,
+    /// `normal`, `dev` or `build`.
+    pub kind: String,
+}
+/// An entry of the `[features]` table.
+#[derive(Debug)]
+pub struct CargoFeature {
+    pub name: String,
+    /// Features, `dep:` optional dependencies and `dep/feature` features of dependencies.
+    pub enables: Vec<String>,
+}
+#[derive(Debug)]
+pub struct CargoCrate {
+    pub name: String,
+    /// Directory containing the manifest of the crate.
+    pub root: PathBuf,
+    pub dependencies: Vec<CargoDependency>,
+    pub features: Vec<CargoFeature>,
+}
+/// The crates of a workspace, read from the local manifests only.
+#[derive(Debug)]
+pub struct CargoWorkspace {
+    pub root: PathBuf,
+    pub crates: Vec<CargoCrate>,
+}
+
+impl CargoWorkspace {
+    /// Reads the manifest in `root` and the manifests of its workspace members.
+    pub fn load(root: &Path) -> Option<Self> {
+        let manifest = read_manifest(root)?;
+        let mut crates = vec![];
+        if manifest.get("package").is_some() {
+            crates.push(parse_crate(root, &manifest, root, &manifest)?);
+        }
+        let members = manifest
+            .get("workspace")
+            .and_then(|x| x.get("members"))
+            .and_then(|x| x.as_array());
+        for member in members.into_iter().flatten().filter_map(|x| x.as_str()) {
+            for dir in expand_member(root, member) {
+                let member_manifest = match read_manifest(&dir) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                if let Some(c) = parse_crate(&dir, &member_manifest, root, &manifest) {
+                    if !crates.iter().any(|x| x.root == c.root) {
+                        crates.push(c);
+                    }
+                }
+            }
+        }
+        Some(CargoWorkspace {
+            root: root.to_path_buf(),
+            crates,
+        })
+    }
+    /// The crate owning the source file at `path`, the one with the innermost root.
+    pub fn crate_for(&self, path: &Path) -> Option<&CargoCrate> {
+        let path = normalize(path);
+        self.crates
+            .iter()
+            .filter(|x| path.starts_with(normalize(&x.root)))
+            .max_by_key(|x| x.root.components().count())
+    }
+    pub fn is_member(&self, name: &str) -> bool {
+        self.crates.iter().any(|x| x.name == name)
+    }
+}
+
+fn read_manifest(dir: &Path) -> Option<Value> {
+    let text = std::fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    text.parse::<Value>().ok()
+}
+
+/// Member directories of a `members` entry, `crates/*` lists all crates below `crates`.
+fn expand_member(root: &Path, member: &str) -> Vec<PathBuf> {
+    match member.strip_suffix("/*") {
+        Some(parent) => {
+            let mut dirs: Vec<PathBuf> = std::fs::read_dir(root.join(parent))
+                .into_iter()
+                .flatten()
+                .filter_map(|x| x.ok())
+                .map(|x| x.path())
+                .filter(|x| x.join("Cargo.toml").is_file())
+                .collect();
+            dirs.sort();
+            dirs
+        }
+        None => vec![root.join(member)],
+    }
+}
+
+fn parse_crate(
+    root: &Path,
+    manifest: &Value,
+    workspace_root: &Path,
+    workspace: &Value,
+) -> Option<CargoCrate> {
+    let name = manifest.get("package")?.get("name")?.as_str()?.to_owned();
+    let mut tables = vec![];
+    for (key, kind) in [
+        ("dependencies", "normal"),
+        ("dev-dependencies", "dev"),
+        ("build-dependencies", "build"),
+    ]
+    .iter()
+    {
+        tables.push((manifest.get(key), *kind));
+        // platform specific dependencies, `[target.'cfg(unix)'.dependencies]`
+        if let Some(targets) = manifest.get("target").and_then(|x| x.as_table()) {
+            for target in targets.values() {
+                tables.push((target.get(key), *kind));
+            }
+        }
+    }
+    let mut dependencies = vec![];
+    for (table, kind) in tables {
+        let table = match table.and_then(|x| x.as_table()) {
+            Some(x) => x,
+            None => continue,
+        };
+        for (key, value) in table {
+            dependencies.push(parse_dependency(
+                root,
+                key,
+                value,
+                kind,
+                workspace_root,
+                workspace,
+            ));
+        }
+    }
+    let features = manifest
+        .get("features")
+        .and_then(|x| x.as_table())
+        .into_iter()
+        .flatten()
+        .map(|(name, enables)| CargoFeature {
+            name: name.to_owned(),
+            enables: enables
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|x| x.as_str())
+                .map(|x| x.to_owned())
+                .collect(),
+        })
+        .collect();
+    Some(CargoCrate {
+        name,
+        root: root.to_path_buf(),
+        dependencies,
+        features,
+    })
+}
+
+fn parse_dependency(
+    root: &Path,
+    key: &str,
+    value: &Value,
+    kind: &str,
+    workspace_root: &Path,
+    workspace: &Value,
+) -> CargoDependency {
+    let optional = value
+        .get("optional")
+        .and_then(|x| x.as_bool())
+        .unwrap_or(false);
+    // `foo = { workspace = true }` inherits from `[workspace.dependencies]`
+    let inherited = match value.get("workspace").and_then(|x| x.as_bool()) {
+        Some(true) => workspace
+            .get("workspace")
+            .and_then(|x| x.get("dependencies"))
+            .and_then(|x| x.get(key)),
+        _ => None,
+    };
+    let value = inherited.unwrap_or(value);
+    let name = value
+        .get("package")
+        .and_then(|x| x.as_str())
+        .unwrap_or(key)
+        .to_owned();
+    // inherited paths are relative to the workspace root
+    let base = match inherited {
+        Some(_) => workspace_root,
+        None => root,
+    };
+    let path = value
+        .get("path")
+        .and_then(|x| x.as_str())
+        .map(|x| normalize(&base.join(x)));
+    CargoDependency {
+        name,
+        key: key.to_owned(),
+        path,
+        optional,
+        kind: kind.to_owned(),
+    }
+}
+
+/// Removes `.` and resolves `..` components without touching the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut v = PathBuf::new();
+    for c in path.components() {
+        match c {
+            Component::CurDir => {}
+            Component::ParentDir if v.file_name().is_some() => {
+                v.pop();
+            }
+            c => v.push(c),
+        }
+    }
+    v
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, text: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+
+    #[test]
+    fn test_cargo_workspace() {
+        let root = std::env::temp_dir().join("umlgen_test_cargo_workspace");
+        let _ = std::fs::remove_dir_all(&root);
+        write(
+            &root.join("Cargo.toml"),
+            "[workspace]
+members = [\"app\", \"crates/*\"]
+[workspace.dependencies]
+core = { path = \"crates/core\" }
+",
+        );
+        write(
+            &root.join("app/Cargo.toml"),
+            "[package]
+name = \"app\"
+[dependencies]
+serde = \"1\"
+core = { workspace = true }
+db = { path = \"../crates/db\", optional = true }
+[features]
+default = [\"std\"]
+std = []
+full = [\"std\", \"dep:db\", \"core/serde\"]
+[dev-dependencies]
+testutil = { path = \"../testutil\" }
+",
+        );
+        write(
+            &root.join("crates/core/Cargo.toml"),
+            "[package]
+name = \"core\"
+",
+        );
+        write(
+            &root.join("crates/db/Cargo.toml"),
+            "[package]
+name = \"db\"
+[dependencies]
+core2 = { package = \"core\", path = \"../core\" }
+",
+        );
+
+        let workspace = CargoWorkspace::load(&root).unwrap();
+        let names: Vec<&str> = workspace.crates.iter().map(|x| x.name.as_str()).collect();
+        assert!(names == vec!["app", "core", "db"]);
+
+        let app = &workspace.crates[0];
+        let serde = app.dependencies.iter().find(|x| x.name == "serde").unwrap();
+        assert!(serde.path.is_none());
+        let core = app.dependencies.iter().find(|x| x.name == "core").unwrap();
+        assert!(core.path == Some(root.join("crates/core")));
+        let db = app.dependencies.iter().find(|x| x.name == "db").unwrap();
+        assert!(db.optional && db.path == Some(root.join("crates/db")));
+        let testutil = app
+            .dependencies
+            .iter()
+            .find(|x| x.name == "testutil")
+            .unwrap();
+        assert!(testutil.kind == "dev");
+        let features: Vec<(&str, &Vec<String>)> = app
+            .features
+            .iter()
+            .map(|x| (x.name.as_str(), &x.enables))
+            .collect();
+        assert!(features.len() == 3);
+        assert!(features[0].0 == "default" && features[0].1 == &vec!["std"]);
+        assert!(features[1].0 == "full" && features[1].1 == &vec!["std", "dep:db", "core/serde"]);
+        let core2 = &workspace.crates[2].dependencies[0];
+        assert!(core2.name == "core" && core2.key == "core2");
+
+        let file = root.join("crates/db/src/lib.rs");
+        assert!(workspace.crate_for(&file).unwrap().name == "db");
+        assert!(workspace.crate_for(&root.join("README.md")).is_none());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
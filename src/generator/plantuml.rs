@@ -1,4 +1,5 @@
-use crate::parser::LangParser;
+use crate::cargo::{CargoCrate, CargoWorkspace};
+use crate::parser::{diamonds, LangParser};
 use crate::uml::*;
 use brotli2::read::BrotliEncoder;
 
@@ -15,12 +16,18 @@ pub fn plantuml_encode(s: String) -> String {
 // }
 impl<T: LangParser> PlantUml for T {
     fn to_plantuml(&self) -> String {
+        // nested classes are named `Outer::Inner`
         format!(
             "@startuml
+set namespaceSeparator none
+{}
+{}
 {}
 @enduml
 ",
-            self.classes().to_plantuml()
+            self.classes().to_plantuml(),
+            self.enums().to_plantuml(),
+            self.blanket_impls().to_plantuml()
         )
     }
 }
@@ -36,7 +43,68 @@ impl PlantUml for Vec<UmlField> {
         vec.join("\n")
     }
 }
+/// Quotes class names with template arguments, which would be read as generics otherwise,
+/// `Buffer<bool>` => `"Buffer<bool>"`.
+fn class_name(name: &str) -> String {
+    match name.contains('<') {
+        true => format!("\"{}\"", name),
+        false => name.to_owned(),
+    }
+}
+/// Packages declaring the given elements, (`net::http`, `class net::http::Server`), so that
+/// they are placed in their namespace before any relation mentions them.
+fn package_declarations(elements: Vec<(&String, String)>) -> Vec<String> {
+    let mut packages: Vec<(&String, Vec<String>)> = vec![];
+    for (package, declaration) in elements {
+        match packages.iter_mut().find(|(x, _)| *x == package) {
+            Some((_, declarations)) => declarations.push(declaration),
+            None => packages.push((package, vec![declaration])),
+        }
+    }
+    packages
+        .iter()
+        .map(|(package, declarations)| {
+            format!(
+                "package \"{}\" {{\n{}\n}}",
+                package,
+                declarations.join("\n")
+            )
+        })
+        .collect()
+}
 impl PlantUml for Vec<UmlClass> {
+    fn to_plantuml(&self) -> String {
+        let mut vec = package_declarations(
+            self.iter()
+                .filter_map(|x| {
+                    let package = x.package.as_ref()?;
+                    Some((package, format!("class {}", class_name(&x.name))))
+                })
+                .collect(),
+        );
+        vec.extend(self.iter().map(|x| x.to_plantuml()));
+        for (class, base) in diamonds(self) {
+            vec.push(format!(
+                "note top of {} : diamond inheritance of {}",
+                class_name(&class),
+                class_name(&base)
+            ));
+        }
+        vec.join("\n")
+    }
+}
+impl PlantUml for Vec<UmlEnum> {
+    fn to_plantuml(&self) -> String {
+        let mut vec = package_declarations(
+            self.iter()
+                .filter_map(|x| Some((x.package.as_ref()?, format!("enum {}", x.name))))
+                .collect(),
+        );
+        vec.extend(self.iter().map(|x| x.to_plantuml()));
+        vec.join("\n")
+    }
+}
+impl PlantUml for Vec<UmlBlanketImpl> {
     fn to_plantuml(&self) -> String {
         let vec: Vec<String> = self.iter().map(|x| x.to_plantuml()).collect();
         vec.join("\n")
@@ -50,44 +118,99 @@ impl PlantUml for Vec<UmlParameter> {
 }
 impl PlantUml for UmlParameter {
     fn to_plantuml(&self) -> String {
-        format!("{name} : {type}",name = self.name,type = self.data_type)
+        // unnamed parameters are shown by their type
+        match self.name.is_empty() {
+            true => self.data_type.clone(),
+            false => format!("{name} : {type}",name = self.name,type = self.data_type),
+        }
     }
 }
 impl PlantUml for UmlVisibility {
     fn to_plantuml(&self) -> String {
         match self {
             UmlVisibility::Public => "+".to_owned(),
+            UmlVisibility::Protected => "#".to_owned(),
             UmlVisibility::Private => "-".to_owned(),
         }
     }
 }
+/// The `{static}` or `{abstract}` prefix, the ` const noexcept` qualifiers and the
+/// ` <<virtual, override>>` stereotypes of a member, following the given `stereotypes`.
+fn member_modifiers(
+    modifiers: &Vec<UmlMemberModifier>,
+    mut stereotypes: Vec<&str>,
+) -> (String, String, String) {
+    let mut prefix = String::new();
+    let mut qualifiers = String::new();
+    for modifier in modifiers {
+        match modifier {
+            UmlMemberModifier::Abstract => prefix.push_str("{abstract} "),
+            UmlMemberModifier::Static => prefix.push_str("{static} "),
+            UmlMemberModifier::Const => qualifiers.push_str(" const"),
+            UmlMemberModifier::Noexcept => qualifiers.push_str(" noexcept"),
+            UmlMemberModifier::Virtual => stereotypes.push("virtual"),
+            UmlMemberModifier::Override => stereotypes.push("override"),
+            UmlMemberModifier::Final => stereotypes.push("final"),
+            UmlMemberModifier::Constexpr => stereotypes.push("constexpr"),
+            UmlMemberModifier::Inline => stereotypes.push("inline"),
+            UmlMemberModifier::Explicit => stereotypes.push("explicit"),
+            UmlMemberModifier::Default => stereotypes.push("default"),
+            UmlMemberModifier::Delete => stereotypes.push("delete"),
+        }
+    }
+    // pure virtual methods are virtual anyway
+    if modifiers.contains(&UmlMemberModifier::Abstract) {
+        stereotypes.retain(|x| *x != "virtual");
+    }
+    let stereotypes = match stereotypes.is_empty() {
+        true => String::new(),
+        false => format!(" <<{}>>", stereotypes.join(", ")),
+    };
+    (prefix, qualifiers, stereotypes)
+}
 impl PlantUml for UmlField {
     fn to_plantuml(&self) -> String {
+        let (prefix, _, stereotypes) = member_modifiers(&self.modifiers, vec![]);
         format!(
-            "{visibility}{name} : {type}",
+            "{prefix}{visibility}{name} : {type}{stereotypes}",
+            prefix = prefix,
             visibility = self.visibility.to_plantuml(),
             name = self.name,
             type = self.data_type,
+            stereotypes = stereotypes,
         )
     }
 }
 impl PlantUml for UmlMethod {
     fn to_plantuml(&self) -> String {
-        match &self.return_type {
+        let name = match self.template_parameters.is_empty() {
+            true => self.name.clone(),
+            false => format!("{}<{}>", self.name, self.template_parameters.join(", ")),
+        };
+        let kind = match self.kind {
+            UmlMethodKind::Constructor => vec!["create"],
+            UmlMethodKind::Destructor => vec!["destroy"],
+            _ => vec![],
+        };
+        let (prefix, qualifiers, stereotypes) = member_modifiers(&self.modifiers, kind);
+        let signature = match &self.return_type {
             Some(return_type) => format!(
-                "{visibility}{name}({parameters}): {return_type}",
+                "{visibility}{name}({parameters}){qualifiers}: {return_type}",
                 visibility = self.visibility.to_plantuml(),
-                name = self.name,
+                name = name,
                 parameters = self.parameters.to_plantuml(),
+                qualifiers = qualifiers,
                 return_type = return_type
             ),
             None => format!(
-                "{visibility}{name}({parameters})",
+                "{visibility}{name}({parameters}){qualifiers}",
                 visibility = self.visibility.to_plantuml(),
-                name = self.name,
-                parameters = self.parameters.to_plantuml()
+                name = name,
+                parameters = self.parameters.to_plantuml(),
+                qualifiers = qualifiers
             ),
-        }
+        };
+        format!("{}{}{}", prefix, signature, stereotypes)
     }
 }
 impl PlantUml for UmlInterface {
@@ -103,31 +226,867 @@ impl PlantUml for UmlInterface {
 }
 impl PlantUml for UmlClass {
     fn to_plantuml(&self) -> String {
+        let name = &class_name(&self.name);
         let assocs: Vec<String> = self
             .associations
             .iter()
-            .map(|x| format!("{} --> {} : {}", self.name, x.to, x.to_title))
+            .map(|x| match (x.polymorphic, x.is_static) {
+                (true, _) => format!("{} --> {} : {} <<polymorphic>>", name, x.to, x.to_title),
+                (false, true) => format!("{} --> {} : {} <<static>>", name, x.to, x.to_title),
+                (false, false) => format!("{} --> {} : {}", name, x.to, x.to_title),
+            })
+            .collect();
+        let dependencies: Vec<String> = self
+            .dependencies
+            .iter()
+            .map(|x| match x.polymorphic {
+                true => format!("{} ..> {} : <<polymorphic>>", name, x.to),
+                false => format!("{} ..> {}", name, x.to),
+            })
+            .collect();
+        let uses: Vec<String> = self
+            .uses
+            .iter()
+            .map(|x| format!("{} ..> {} : <<uses>>", name, x))
             .collect();
         let extends: Vec<String> = self
             .extends
             .iter()
-            .map(|x| format!("{} --|> {} ", self.name, x))
+            .map(|x| {
+                // `: protected virtual Base` => `<<protected>> <<virtual>>`
+                let mut labels = vec![];
+                match x.access {
+                    UmlVisibility::Public => {}
+                    UmlVisibility::Protected => labels.push("<<protected>>"),
+                    UmlVisibility::Private => labels.push("<<private>>"),
+                }
+                if x.is_virtual {
+                    labels.push("<<virtual>>");
+                }
+                match labels.is_empty() {
+                    true => format!("{} --|> {} ", name, class_name(&x.name)),
+                    false => format!(
+                        "{} --|> {} : {}",
+                        name,
+                        class_name(&x.name),
+                        labels.join(" ")
+                    ),
+                }
+            })
+            .collect();
+        let implements: Vec<String> = self
+            .implements
+            .iter()
+            .map(|x| format!("{} ..|> {}", name, x))
             .collect();
+        let owner = match &self.owner {
+            Some(owner) => format!("{} +-- {}", class_name(owner), name),
+            None => String::new(),
+        };
+        let specializes = match &self.specializes {
+            Some(primary) => format!("{} ..> {} : <<specializes>>", name, class_name(primary)),
+            None => String::new(),
+        };
+        let keyword = match self.modifier {
+            Some(UmlClassModifier::Abstract) => "abstract class",
+            Some(UmlClassModifier::Interface) => "interface",
+            None => "class",
+        };
+        // partial specializations show their parameters in the name already
+        let generics = match self.template_parameters.is_empty() || self.specializes.is_some() {
+            true => String::new(),
+            false => format!("<{}>", self.template_parameters.join(", ")),
+        };
         return format!(
             "
-class {name} {{
+{keyword} {name}{generics} {{
 {fields}
 {methods}
 }}
+{owner}
+{specializes}
 {assocs}
+{dependencies}
+{uses}
 {extends}
+{implements}
 ",
-            name = self.name,
+            keyword = keyword,
+            name = name,
+            generics = generics,
             fields = self.fields.to_plantuml(),
             methods = self.methods.to_plantuml(),
+            owner = owner,
+            specializes = specializes,
             assocs = assocs.join("\n"),
+            dependencies = dependencies.join("\n"),
+            uses = uses.join("\n"),
             extends = extends.join("\n"),
+            implements = implements.join("\n"),
+        );
+    }
+}
+impl PlantUml for UmlEnum {
+    fn to_plantuml(&self) -> String {
+        let implements: Vec<String> = self
+            .implements
+            .iter()
+            .map(|x| format!("{} ..|> {}", self.name, x))
+            .collect();
+        let uses: Vec<String> = self
+            .uses
+            .iter()
+            .map(|x| format!("{} ..> {} : <<uses>>", self.name, x))
+            .collect();
+        // `enum class Mode : uint8_t` => `enum Mode <<enum class : uint8_t>>`
+        let keyword = match self.is_scoped {
+            true => "enum class",
+            false => "enum",
+        };
+        let stereotype = match (&self.underlying_type, self.is_scoped) {
+            (Some(underlying_type), _) => format!(" <<{} : {}>>", keyword, underlying_type),
+            (None, true) => format!(" <<{}>>", keyword),
+            (None, false) => String::new(),
+        };
+        let variants: Vec<String> = self
+            .variants
+            .iter()
+            .zip(&self.values)
+            .map(|(variant, value)| match value {
+                Some(value) => format!("{} = {}", variant, value),
+                None => variant.to_owned(),
+            })
+            .collect();
+        let owner = match &self.owner {
+            Some(owner) => format!("{} +-- {}", class_name(owner), self.name),
+            None => String::new(),
+        };
+        format!(
+            "
+enum {name}{stereotype} {{
+{variants}
+{methods}
+}}
+{owner}
+{implements}
+{uses}
+",
+            name = self.name,
+            stereotype = stereotype,
+            variants = variants.join("\n"),
+            methods = self.methods.to_plantuml(),
+            owner = owner,
+            implements = implements.join("\n"),
+            uses = uses.join("\n"),
+        )
+    }
+}
+impl PlantUml for Vec<UmlActivity> {
+    fn to_plantuml(&self) -> String {
+        let vec: Vec<String> = self.iter().map(|x| x.to_plantuml()).collect();
+        vec.join("\n")
+    }
+}
+impl PlantUml for UmlActivity {
+    fn to_plantuml(&self) -> String {
+        match self {
+            UmlActivity::Action(text) => format!(":{};", text),
+            UmlActivity::Decision {
+                condition,
+                then,
+                otherwise,
+            } => match otherwise.is_empty() {
+                true => format!(
+                    "if ({}) then (yes)\n{}\nendif",
+                    condition,
+                    then.to_plantuml()
+                ),
+                false => format!(
+                    "if ({}) then (yes)\n{}\nelse (no)\n{}\nendif",
+                    condition,
+                    then.to_plantuml(),
+                    otherwise.to_plantuml()
+                ),
+            },
+            UmlActivity::Switch { subject, cases } => {
+                let cases: Vec<String> = cases
+                    .iter()
+                    .map(|x| format!("case ({})\n{}", x.pattern, x.activities.to_plantuml()))
+                    .collect();
+                format!("switch ({})\n{}\nendswitch", subject, cases.join("\n"))
+            }
+            UmlActivity::Loop { condition, body } => {
+                format!("while ({})\n{}\nendwhile", condition, body.to_plantuml())
+            }
+            UmlActivity::Repeat { condition, body } => {
+                format!(
+                    "repeat\n{}\nrepeat while ({})",
+                    body.to_plantuml(),
+                    condition
+                )
+            }
+            UmlActivity::Try(text) => format!(":{};\nif (error) then (yes)\nstop\nendif", text),
+            UmlActivity::Return(text) => format!(":{};\nstop", text),
+        }
+    }
+}
+impl PlantUml for UmlActivityDiagram {
+    fn to_plantuml(&self) -> String {
+        // a trailing return already ends the flow
+        let stop = match self.activities.last() {
+            Some(UmlActivity::Return(_)) => "",
+            _ => "stop\n",
+        };
+        format!(
+            "@startuml
+title {name}
+start
+{activities}
+{stop}@enduml
+",
+            name = self.name,
+            activities = self.activities.to_plantuml(),
+            stop = stop,
+        )
+    }
+}
+impl PlantUml for Vec<UmlStateMachine> {
+    fn to_plantuml(&self) -> String {
+        let vec: Vec<String> = self.iter().map(|x| x.to_plantuml()).collect();
+        format!(
+            "@startuml
+{}
+@enduml
+",
+            vec.join("\n")
+        )
+    }
+}
+impl PlantUml for UmlStateMachine {
+    fn to_plantuml(&self) -> String {
+        // variants of different enums may share a name, so states get a qualified alias
+        let states: Vec<String> = self
+            .states
+            .iter()
+            .map(|x| format!("state \"{}\" as {}_{}", x, self.name, x))
+            .collect();
+        let transitions: Vec<String> = self
+            .transitions
+            .iter()
+            .map(|x| {
+                format!(
+                    "{name}_{from} --> {name}_{to} : {label}",
+                    name = self.name,
+                    from = x.from,
+                    to = x.to,
+                    label = x.label
+                )
+            })
+            .collect();
+        format!(
+            "state {name} {{
+{states}
+{transitions}
+}}",
+            name = self.name,
+            states = states.join("\n"),
+            transitions = transitions.join("\n"),
+        )
+    }
+}
+impl PlantUml for UmlBlanketImpl {
+    fn to_plantuml(&self) -> String {
+        let link = format!(
+            "{trait_name} ..> \"{target}\" : <<blanket impl>>",
+            trait_name = self.trait_name,
+            target = self.target
         );
+        match self.bounds.is_empty() {
+            true => link,
+            false => format!(
+                "{link}
+note on link
+{bounds}
+end note",
+                link = link,
+                bounds = self.bounds.join("\n")
+            ),
+        }
+    }
+}
+impl PlantUml for UmlConversion {
+    fn to_plantuml(&self) -> String {
+        format!(
+            "\"{from}\" --> \"{to}\" : {kind}",
+            from = self.from,
+            to = self.to,
+            kind = self.kind
+        )
+    }
+}
+impl PlantUml for UmlConversionGraph {
+    fn to_plantuml(&self) -> String {
+        let errors: Vec<String> = self
+            .error_types
+            .iter()
+            .map(|x| format!("class \"{}\" <<error>> #FFDDDD", x))
+            .collect();
+        let conversions: Vec<String> = self.conversions.iter().map(|x| x.to_plantuml()).collect();
+        format!(
+            "@startuml
+{errors}
+{conversions}
+@enduml
+",
+            errors = errors.join("\n"),
+            conversions = conversions.join("\n"),
+        )
+    }
+}
+impl PlantUml for Vec<UmlModule> {
+    fn to_plantuml(&self) -> String {
+        let components: Vec<String> = self.iter().map(|x| format!("[{}]", x.name)).collect();
+        let dependencies: Vec<String> = self
+            .iter()
+            .filter(|x| !x.dependencies.is_empty())
+            .map(|x| x.to_plantuml())
+            .collect();
+        format!(
+            "@startuml
+{components}
+{dependencies}
+@enduml
+",
+            components = components.join("\n"),
+            dependencies = dependencies.join("\n"),
+        )
+    }
+}
+impl PlantUml for UmlModule {
+    fn to_plantuml(&self) -> String {
+        let dependencies: Vec<String> = self
+            .dependencies
+            .iter()
+            .map(|x| format!("[{}] ..> [{}] : {}", self.name, x.to, x.weight))
+            .collect();
+        dependencies.join("\n")
+    }
+}
+/// An entity of `schema` with its keys above the other columns.
+fn entity_declaration(schema: &UmlSchema, entity: &UmlEntity) -> String {
+    let column = |x: &UmlColumn| {
+        let foreign_key = schema
+            .joins
+            .iter()
+            .any(|j| j.from == entity.name && j.column == x.name);
+        format!(
+            "{}{} : {}{}{}",
+            if x.primary_key { "* " } else { "" },
+            x.name,
+            x.data_type,
+            if x.primary_key { " <<PK>>" } else { "" },
+            if foreign_key { " <<FK>>" } else { "" }
+        )
+    };
+    let keys: Vec<String> = entity
+        .columns
+        .iter()
+        .filter(|x| x.primary_key)
+        .map(column)
+        .collect();
+    let columns: Vec<String> = entity
+        .columns
+        .iter()
+        .filter(|x| !x.primary_key)
+        .map(column)
+        .collect();
+    format!(
+        "entity {} {{\n{}\n--\n{}\n}}",
+        entity.name,
+        keys.join("\n"),
+        columns.join("\n")
+    )
+}
+impl PlantUml for UmlSchema {
+    fn to_plantuml(&self) -> String {
+        let mut lines = vec![];
+        // tables queried together are laid out next to each other
+        let mut declared: Vec<&str> = vec![];
+        for group in self.query_groups.iter() {
+            let entities: Vec<&UmlEntity> = self
+                .entities
+                .iter()
+                .filter(|x| group.contains(&x.name) && !declared.contains(&x.name.as_str()))
+                .collect();
+            if entities.len() < 2 {
+                continue;
+            }
+            lines.push("together {".to_owned());
+            for x in entities {
+                lines.push(entity_declaration(self, x));
+                declared.push(&x.name);
+            }
+            lines.push("}".to_owned());
+        }
+        for x in self.entities.iter() {
+            if !declared.contains(&x.name.as_str()) {
+                lines.push(entity_declaration(self, x));
+                declared.push(&x.name);
+            }
+        }
+        // sqlx queries tables which are declared by the database only
+        for mapping in self.mappings.iter() {
+            if !declared.contains(&mapping.table.as_str()) {
+                lines.push(format!("entity {}", mapping.table));
+                declared.push(&mapping.table);
+            }
+        }
+        for join in self.joins.iter() {
+            lines.push(format!(
+                "{} }}o--|| {} : {}",
+                join.from, join.to, join.column
+            ));
+        }
+        for mapping in self.mappings.iter() {
+            lines.push(format!(
+                "class {} <<{}>>",
+                mapping.type_name,
+                mapping.derives.join(", ")
+            ));
+            lines.push(format!(
+                "{} ..> {} : <<table>>",
+                mapping.type_name, mapping.table
+            ));
+        }
+        format!(
+            "@startuml
+{}
+@enduml
+",
+            lines.join("\n")
+        )
+    }
+}
+impl PlantUml for UmlMacroInventory {
+    fn to_plantuml(&self) -> String {
+        let id = |module: &str, name: &str| {
+            format!("{}_{}", module, name).replace(|x: char| !x.is_alphanumeric(), "_")
+        };
+        let mut modules: Vec<&str> = vec![];
+        for module in self
+            .macros
+            .iter()
+            .map(|x| x.module.as_str())
+            .chain(self.invocations.iter().map(|x| x.module.as_str()))
+        {
+            if !modules.contains(&module) {
+                modules.push(module);
+            }
+        }
+        let mut lines = vec![];
+        let mut links = vec![];
+        for module in modules {
+            lines.push(format!("package \"{}\" {{", module));
+            for m in self.macros.iter().filter(|x| x.module == module) {
+                lines.push(format!(
+                    "class \"{}!\" as {} <<macro>> {{\n{}\n}}",
+                    m.name,
+                    id(module, &m.name),
+                    m.rules.join("\n")
+                ));
+            }
+            for (i, invocation) in self.invocations.iter().enumerate() {
+                if invocation.module != module {
+                    continue;
+                }
+                let invocation_id = id(module, &format!("invocation_{}", i));
+                let label = match &invocation.owner {
+                    Some(owner) => format!("{}! in impl {}", invocation.name, owner),
+                    None => format!("{}!", invocation.name),
+                };
+                lines.push(format!(
+                    "rectangle \"{}\" as {} <<generated items>>",
+                    label, invocation_id
+                ));
+                // the definition in the same module shadows the others
+                let name = invocation.name.rsplit("::").next().unwrap();
+                let definition = self
+                    .macros
+                    .iter()
+                    .filter(|x| x.name == name)
+                    .max_by_key(|x| x.module == module);
+                if let Some(definition) = definition {
+                    links.push(format!(
+                        "{} ..> {} : expands",
+                        invocation_id,
+                        id(&definition.module, &definition.name)
+                    ));
+                }
+            }
+            lines.push("}".to_owned());
+        }
+        lines.append(&mut links);
+        format!(
+            "@startuml
+{}
+@enduml
+",
+            lines.join("\n")
+        )
+    }
+}
+impl PlantUml for CargoWorkspace {
+    fn to_plantuml(&self) -> String {
+        let mut lines: Vec<String> = self
+            .crates
+            .iter()
+            .map(|x| format!("[{}]", x.name))
+            .collect();
+        for c in self.crates.iter() {
+            // registry dependencies are outside of the workspace
+            for dependency in c
+                .dependencies
+                .iter()
+                .filter(|x| x.path.is_some() || self.is_member(&x.name))
+            {
+                let arrow = match dependency.kind.as_str() {
+                    "normal" => "-->",
+                    _ => "..>",
+                };
+                let mut labels = vec![];
+                if dependency.kind != "normal" {
+                    labels.push(dependency.kind.clone());
+                }
+                if dependency.optional {
+                    labels.push("<<optional>>".to_owned());
+                }
+                let label = match labels.is_empty() {
+                    true => String::new(),
+                    false => format!(" : {}", labels.join(" ")),
+                };
+                lines.push(format!(
+                    "[{}] {} [{}]{}",
+                    c.name, arrow, dependency.name, label
+                ));
+            }
+        }
+        format!(
+            "@startuml
+{}
+@enduml
+",
+            lines.join("\n")
+        )
+    }
+}
+/// Features of the workspace crates with the features and optional dependencies they enable.
+pub struct FeatureDiagram<'a> {
+    pub workspace: &'a CargoWorkspace,
+    /// Items gated by a feature, by the name of their crate.
+    pub gates: Vec<(String, &'a UmlFeatureGate)>,
+}
+impl<'a> FeatureDiagram<'a> {
+    fn render_crate(&self, c: &CargoCrate, lines: &mut Vec<String>) {
+        let id = |kind: &str, name: &str| {
+            format!("{}_{}_{}", c.name, kind, name).replace(|x: char| !x.is_alphanumeric(), "_")
+        };
+        let mut nodes = vec![];
+        let mut edges = vec![];
+        let mut dependencies: Vec<String> = vec![];
+        for feature in c.features.iter() {
+            nodes.push(format!(
+                "rectangle \"{}\" as {}",
+                feature.name,
+                id("feature", &feature.name)
+            ));
+            for enabled in feature.enables.iter() {
+                let from = id("feature", &feature.name);
+                // `dep:db`, `db/serde`, the weak `db?/serde` or another feature
+                let (to, label) = match (enabled.strip_prefix("dep:"), enabled.find('/')) {
+                    (Some(dependency), _) => (id("dependency", dependency), "enables".to_owned()),
+                    (None, Some(i)) => {
+                        let dependency = enabled[..i].trim_end_matches('?');
+                        if !dependencies.iter().any(|x| x == dependency) {
+                            dependencies.push(dependency.to_owned());
+                        }
+                        (id("dependency", dependency), enabled[i + 1..].to_owned())
+                    }
+                    (None, None) => (id("feature", enabled), "enables".to_owned()),
+                };
+                edges.push(format!("{} --> {} : {}", from, to, label));
+            }
+        }
+        // features name dependencies by their key, `core2` of `core2 = { package = "core" }`
+        for dependency in c.dependencies.iter().filter(|x| x.optional) {
+            if !dependencies.contains(&dependency.key) {
+                dependencies.push(dependency.key.clone());
+            }
+            // optional dependencies not named by `dep:` get an implicit feature of their name
+            let explicit = c
+                .features
+                .iter()
+                .flat_map(|x| x.enables.iter())
+                .any(|x| x.strip_prefix("dep:") == Some(&dependency.key));
+            if !explicit && !c.features.iter().any(|x| x.name == dependency.key) {
+                nodes.push(format!(
+                    "rectangle \"{}\" as {}",
+                    dependency.key,
+                    id("feature", &dependency.key)
+                ));
+                edges.push(format!(
+                    "{} --> {} : enables",
+                    id("feature", &dependency.key),
+                    id("dependency", &dependency.key)
+                ));
+            }
+        }
+        if nodes.is_empty() && dependencies.is_empty() {
+            return;
+        }
+        for dependency in dependencies.iter() {
+            let optional = c
+                .dependencies
+                .iter()
+                .any(|x| x.optional && x.key == *dependency);
+            nodes.push(format!(
+                "component \"{}\" as {}{}",
+                dependency,
+                id("dependency", dependency),
+                if optional { " <<optional>>" } else { "" }
+            ));
+        }
+        lines.push(format!(
+            "package \"{}\" {{\n{}\n}}",
+            c.name,
+            nodes.join("\n")
+        ));
+        lines.append(&mut edges);
+        for feature in c.features.iter() {
+            let items: Vec<&str> = self
+                .gates
+                .iter()
+                .filter(|(name, gate)| *name == c.name && gate.feature == feature.name)
+                .map(|(_, gate)| gate.item.as_str())
+                .collect();
+            if !items.is_empty() {
+                lines.push(format!(
+                    "note right of {}\n{}\nend note",
+                    id("feature", &feature.name),
+                    items.join("\n")
+                ));
+            }
+        }
+    }
+}
+impl<'a> PlantUml for FeatureDiagram<'a> {
+    fn to_plantuml(&self) -> String {
+        let mut lines = vec![];
+        for c in self.workspace.crates.iter() {
+            self.render_crate(c, &mut lines);
+        }
+        format!(
+            "@startuml
+{}
+@enduml
+",
+            lines.join("\n")
+        )
+    }
+}
+/// The FFI functions of a crate next to the matching functions of the C/C++ side.
+pub struct FfiDiagram<'a> {
+    pub boundary: &'a UmlFfiBoundary,
+    pub c_functions: &'a Vec<UmlFfiFunction>,
+    /// Structs of the C/C++ side, paired with the `#[repr(C)]` types of the same name.
+    pub c_types: &'a Vec<UmlClass>,
+}
+impl<'a> FfiDiagram<'a> {
+    fn render_function(id: &str, function: &UmlFfiFunction) -> String {
+        let signature = match &function.return_type {
+            Some(return_type) => format!(
+                "{}({}): {}",
+                function.name,
+                function.parameters.to_plantuml(),
+                return_type
+            ),
+            None => format!("{}({})", function.name, function.parameters.to_plantuml()),
+        };
+        format!(
+            "class \"{}\" as {} <<{}>> {{\n{}\n}}",
+            function.name, id, function.abi, signature
+        )
+    }
+    /// `#[repr(C)]` types passed to or returned from `function`.
+    fn types(&self, function: &UmlFfiFunction) -> Vec<&String> {
+        let types = function
+            .parameters
+            .iter()
+            .map(|x| x.data_type.as_str())
+            .chain(function.return_type.iter().map(|x| x.as_str()))
+            .flat_map(|x| x.split(|c: char| !(c.is_alphanumeric() || c == '_')));
+        self.boundary
+            .types
+            .iter()
+            .filter(|x| types.clone().any(|t| t == x.as_str()))
+            .collect()
+    }
+}
+impl<'a> PlantUml for FfiDiagram<'a> {
+    fn to_plantuml(&self) -> String {
+        let mut lines = vec![];
+        let mut links = vec![];
+        let sides = [
+            ("imports", "extern blocks", &self.boundary.imports),
+            ("exports", "#[no_mangle]", &self.boundary.exports),
+        ];
+        let mut c_functions: Vec<&UmlFfiFunction> = vec![];
+        for (side, title, functions) in sides.iter() {
+            lines.push(format!("package \"{}\" {{", title));
+            for function in functions.iter() {
+                let id = format!("{}_{}", side, function.name);
+                lines.push(FfiDiagram::render_function(&id, function));
+                for t in self.types(function) {
+                    links.push(format!("{} ..> {}", id, t));
+                }
+                // imports call into the C side, exports are called from it
+                if let Some(c) = self.c_functions.iter().find(|x| x.name == function.name) {
+                    match *side {
+                        "imports" => links.push(format!("{} --> c_{} : links", id, c.name)),
+                        _ => links.push(format!("c_{} --> {} : links", c.name, id)),
+                    }
+                    if !c_functions.iter().any(|x| x.name == c.name) {
+                        c_functions.push(c);
+                    }
+                }
+            }
+            lines.push("}".to_owned());
+        }
+        let c_types: Vec<&UmlClass> = self
+            .boundary
+            .types
+            .iter()
+            .filter_map(|t| self.c_types.iter().find(|x| x.name == *t))
+            .collect();
+        if !c_functions.is_empty() || !c_types.is_empty() {
+            lines.push("package \"C/C++\" {".to_owned());
+            for c in c_functions {
+                lines.push(FfiDiagram::render_function(&format!("c_{}", c.name), c));
+            }
+            for c in c_types.iter() {
+                let fields: Vec<String> = c
+                    .fields
+                    .iter()
+                    .map(|x| format!("{} {}", x.data_type, x.name))
+                    .collect();
+                lines.push(format!(
+                    "class \"{}\" as c_type_{} <<struct>> {{\n{}\n}}",
+                    c.name,
+                    c.name,
+                    fields.join("\n")
+                ));
+            }
+            lines.push("}".to_owned());
+        }
+        for t in self.boundary.types.iter() {
+            lines.push(format!("class {} <<repr(C)>>", t));
+            if c_types.iter().any(|x| x.name == *t) {
+                links.push(format!("{} .. c_type_{} : same layout", t, t));
+            }
+        }
+        lines.append(&mut links);
+        format!(
+            "@startuml
+{}
+@enduml
+",
+            lines.join("\n")
+        )
+    }
+}
+/// Class diagram with the classes of each crate drawn in a package named after the crate.
+pub struct GroupedClassDiagram<'a, P: LangParser> {
+    pub groups: &'a Vec<(String, P)>,
+}
+impl<'a, P: LangParser> PlantUml for GroupedClassDiagram<'a, P> {
+    fn to_plantuml(&self) -> String {
+        let packages: Vec<String> = self
+            .groups
+            .iter()
+            .map(|(name, p)| {
+                format!(
+                    "package \"{}\" {{
+{}
+{}
+{}
+}}",
+                    name,
+                    p.classes().to_plantuml(),
+                    p.enums().to_plantuml(),
+                    p.blanket_impls().to_plantuml()
+                )
+            })
+            .collect();
+        format!(
+            "@startuml
+{}
+@enduml
+",
+            packages.join("\n")
+        )
+    }
+}
+/// Sequence diagram following the calls of `entry` (`Class::method`) through `classes`.
+pub struct SequenceDiagram<'a> {
+    pub classes: &'a Vec<UmlClass>,
+    pub entry: String,
+    pub depth: usize,
+}
+impl<'a> SequenceDiagram<'a> {
+    fn method(&self, class: &str, method: &str) -> Option<&UmlMethod> {
+        self.classes
+            .iter()
+            .filter(|x| x.name == class)
+            .flat_map(|x| x.methods.iter())
+            .find(|x| x.name == method)
+    }
+    fn render_calls(&self, class: &str, method: &str, depth: usize, lines: &mut Vec<String>) {
+        let method = match self.method(class, method) {
+            Some(method) if depth > 0 => method,
+            _ => return,
+        };
+        for call in method.calls.iter() {
+            match &call.receiver {
+                Some(receiver) if self.method(receiver, &call.method).is_some() => {
+                    lines.push(format!("{} -> {} : {}()", class, receiver, call.method));
+                    lines.push(format!("activate {}", receiver));
+                    self.render_calls(receiver, &call.method, depth - 1, lines);
+                    lines.push(format!("deactivate {}", receiver));
+                }
+                _ => lines.push(format!("note right of {} : {}", class, call.text)),
+            }
+        }
+    }
+}
+impl<'a> PlantUml for SequenceDiagram<'a> {
+    fn to_plantuml(&self) -> String {
+        let segments: Vec<&str> = self.entry.rsplitn(3, "::").collect();
+        let mut lines = vec![];
+        match segments.as_slice() {
+            [method, class, ..] if self.method(class, method).is_some() => {
+                lines.push(format!("participant {}", class));
+                lines.push(format!("activate {}", class));
+                self.render_calls(class, method, self.depth, &mut lines);
+                lines.push(format!("deactivate {}", class));
+            }
+            _ => lines.push(format!("note \"{} not found\" as N", self.entry)),
+        }
+        format!(
+            "@startuml
+{}
+@enduml
+",
+            lines.join("\n")
+        )
     }
 }
 pub trait PlantUml {
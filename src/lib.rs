@@ -3,6 +3,7 @@
 //     println!("Hello world!!!");
 // }
 
+pub mod cargo;
 pub mod generator;
 pub mod parser;
 pub mod uml;
@@ -1,28 +1,300 @@
+use clap::{App, Arg};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use umlgen::cargo::CargoWorkspace;
 use umlgen::generator::plantuml::*;
 use umlgen::parser::*;
-// use crate::parser::{cpp_parser, rust_parser};
-use std::io::Read;
-// use umlgen::hello;
-
 
-fn main() {
-    // umlgen::hello();
-    // let mut p = cpp_parser::CppParser::new();
-    let mut p = rust_parser::RustParser::new();
-    // find language
+/// Reads all files below the working directory with one of the given extensions.
+fn source_files(extensions: &[&str]) -> Vec<(PathBuf, Vec<u8>)> {
+    let mut files = vec![];
     for entry in walkdir::WalkDir::new(".")
         .follow_links(true)
         .into_iter()
         .filter_map(|e| e.ok())
     {
         let f_name = entry.file_name().to_string_lossy();
-        if f_name.ends_with(".rs") {
-            let mut buf: Vec<u8> = vec![];
-            let mut file = std::fs::File::open(entry.path()).unwrap();
-            let _ = file.read_to_end(&mut buf);
-            // match language {}
-            p.parse(&buf);
+        if extensions.iter().any(|x| f_name.ends_with(x)) {
+            files.push((entry.path().to_path_buf(), read_file(entry.path())));
+        }
+    }
+    files
+}
+
+/// A C/C++ unit by its path from the working directory, `net/socket.h`.
+fn unit_path(path: &Path) -> String {
+    let path = path.strip_prefix(".").unwrap_or(path);
+    let segments: Vec<_> = path.iter().map(|x| x.to_string_lossy()).collect();
+    segments.join("/")
+}
+
+fn read_file(path: &Path) -> Vec<u8> {
+    let mut buf: Vec<u8> = vec![];
+    let mut file = std::fs::File::open(path).unwrap();
+    let _ = file.read_to_end(&mut buf);
+    buf
+}
+
+fn activity<P: LangParser>(
+    p: &mut P,
+    files: &Vec<(PathBuf, Vec<u8>)>,
+    function: &str,
+    lang: &LangChoice,
+) -> String {
+    let mut diagrams = vec![];
+    for (path, buf) in files {
+        // rust functions are addressed by module path, e.g. `crate::sync::Worker::run`
+        let local = match lang {
+            LangChoice::RUST if function.starts_with("crate::") => {
+                let module = format!("{}::", module_path(path));
+                match function.strip_prefix(&module) {
+                    Some(local) => local,
+                    None => continue,
+                }
+            }
+            _ => function,
+        };
+        for mut diagram in p.parse_activity(buf, local) {
+            // titled as asked for, told apart by their full path
+            let qualified = match lang {
+                LangChoice::RUST => {
+                    let qualified = format!("{}::{}", module_path(path), diagram.name);
+                    diagram.name = function.to_owned();
+                    qualified
+                }
+                LangChoice::CPP => diagram.name.clone(),
+            };
+            diagrams.push((qualified, diagram));
+        }
+    }
+    match diagrams.len() {
+        0 => eprintln!("function {} not found", function),
+        1 => return diagrams[0].1.to_plantuml(),
+        _ => {
+            let names: Vec<&str> = diagrams.iter().map(|(x, _)| x.as_str()).collect();
+            let hint = match lang {
+                LangChoice::RUST => "give the module path",
+                LangChoice::CPP => "give the parameters",
+            };
+            eprintln!(
+                "function {} is ambiguous, {} of one of {}",
+                function,
+                hint,
+                names.join(", ")
+            );
+        }
+    }
+    std::process::exit(1);
+}
+
+fn main() {
+    let matches = App::new("umlgen")
+        .about("Generate plantuml code from source code")
+        .arg(
+            Arg::with_name("diagram")
+                .short("d")
+                .long("diagram")
+                .takes_value(true)
+                .possible_values(&[
+                    "class",
+                    "conversions",
+                    "state",
+                    "activity",
+                    "sequence",
+                    "modules",
+                    "crates",
+                    "features",
+                    "er",
+                    "macros",
+                    "ffi",
+                ])
+                .default_value("class")
+                .help("Kind of diagram to generate"),
+        )
+        .arg(
+            Arg::with_name("lang")
+                .short("l")
+                .long("lang")
+                .takes_value(true)
+                .possible_values(&["rust", "cpp"])
+                .default_value("rust")
+                .help("Language of the source files"),
+        )
+        .arg(
+            Arg::with_name("function")
+                .short("f")
+                .long("function")
+                .takes_value(true)
+                .required_ifs(&[("diagram", "activity"), ("diagram", "sequence")])
+                .help("Path of the function to diagram, e.g. crate::sync::Worker::run"),
+        )
+        .arg(
+            Arg::with_name("body-dependencies")
+                .long("body-dependencies")
+                .help("Draw types used in method bodies as <<uses>> dependencies"),
+        )
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .takes_value(true)
+                .default_value("3")
+                .help("Depth of calls followed by the sequence diagram"),
+        )
+        .arg(
+            Arg::with_name("expanded")
+                .long("expanded")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Parse a pre-expanded source file, e.g. from cargo expand, in place of ./**/*.rs"),
+        )
+        .arg(
+            Arg::with_name("group-by-crate")
+                .long("group-by-crate")
+                .help("Draw the classes of each workspace crate in a package"),
+        )
+        .get_matches();
+    let diagram = matches.value_of("diagram").unwrap();
+    match matches.value_of("lang") {
+        Some("cpp") => {
+            let files = source_files(&[".h", ".hpp", ".cpp", ".cc", ".cxx"]);
+            let mut p = cpp_parser::CppParser::new();
+            match diagram {
+                "activity" => {
+                    let function = matches.value_of("function").unwrap();
+                    println!("{}", activity(&mut p, &files, function, &LangChoice::CPP));
+                }
+                "class" | "modules" => {
+                    for (path, buf) in &files {
+                        p.parse_module(&unit_path(path), buf);
+                    }
+                    p.finish();
+                    match diagram {
+                        "modules" => println!("{}", p.modules().to_plantuml()),
+                        _ => println!("{}", p.to_plantuml()),
+                    }
+                }
+                _ => {
+                    eprintln!("{} diagrams are only supported for rust", diagram);
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => {
+            let expanded: Vec<PathBuf> = matches
+                .values_of("expanded")
+                .into_iter()
+                .flatten()
+                .map(PathBuf::from)
+                .collect();
+            let files = match expanded.is_empty() {
+                true => source_files(&[".rs"]),
+                false => expanded
+                    .iter()
+                    .map(|x| (x.to_owned(), read_file(x)))
+                    .collect(),
+            };
+            // a pre-expanded file holds the whole crate with inline modules
+            let module = |path: &Path| match expanded.is_empty() {
+                true => module_path(path),
+                false => "crate".to_owned(),
+            };
+            let mut p = rust_parser::RustParser::new();
+            p.set_scan_bodies(matches.is_present("body-dependencies"));
+            p.set_diagram(diagram);
+            if diagram == "activity" {
+                let function = matches.value_of("function").unwrap();
+                println!("{}", activity(&mut p, &files, function, &LangChoice::RUST));
+                return;
+            }
+            let workspace = CargoWorkspace::load(Path::new("."));
+            if diagram == "crates" || diagram == "features" {
+                let workspace = match workspace {
+                    Some(workspace) => workspace,
+                    None => {
+                        eprintln!("no Cargo.toml found in the working directory");
+                        std::process::exit(1);
+                    }
+                };
+                if diagram == "crates" {
+                    println!("{}", workspace.to_plantuml());
+                    return;
+                }
+                // remember the crate of the items gated in each file
+                let mut crates = vec![];
+                for (path, buf) in &files {
+                    p.parse_module(&module(path), buf);
+                    let name = workspace
+                        .crate_for(path)
+                        .map_or(String::new(), |x| x.name.clone());
+                    crates.resize(p.feature_gates().len(), name);
+                }
+                let features = FeatureDiagram {
+                    workspace: &workspace,
+                    gates: crates.into_iter().zip(p.feature_gates().iter()).collect(),
+                };
+                println!("{}", features.to_plantuml());
+                return;
+            }
+            if diagram == "class" && matches.is_present("group-by-crate") {
+                let mut groups: Vec<(String, rust_parser::RustParser)> = vec![];
+                for (path, buf) in &files {
+                    let name = workspace
+                        .as_ref()
+                        .and_then(|x| x.crate_for(path))
+                        .map_or("(no crate)".to_owned(), |x| x.name.clone());
+                    let i = match groups.iter().position(|(x, _)| *x == name) {
+                        Some(i) => i,
+                        None => {
+                            let mut p = rust_parser::RustParser::new();
+                            p.set_scan_bodies(matches.is_present("body-dependencies"));
+                            p.set_diagram(diagram);
+                            groups.push((name, p));
+                            groups.len() - 1
+                        }
+                    };
+                    groups[i].1.parse_module(&module(path), buf);
+                }
+                for (_, p) in groups.iter_mut() {
+                    p.finish();
+                }
+                println!("{}", GroupedClassDiagram { groups: &groups }.to_plantuml());
+                return;
+            }
+            for (path, buf) in &files {
+                p.parse_module(&module(path), buf);
+            }
+            p.finish();
+            match diagram {
+                "modules" => println!("{}", p.modules().to_plantuml()),
+                "er" => println!("{}", p.schema().to_plantuml()),
+                "macros" => println!("{}", p.macro_inventory().to_plantuml()),
+                "ffi" => {
+                    // the C/C++ side of the boundary, when it is part of the sources
+                    let mut c = cpp_parser::CppParser::new();
+                    for (path, buf) in source_files(&[".h", ".hpp", ".c", ".cpp", ".cc", ".cxx"]) {
+                        c.parse_module(&unit_path(&path), &buf);
+                    }
+                    c.finish();
+                    let ffi = FfiDiagram {
+                        boundary: p.ffi(),
+                        c_functions: c.functions(),
+                        c_types: c.classes(),
+                    };
+                    println!("{}", ffi.to_plantuml());
+                }
+                "conversions" => println!("{}", p.conversion_graph().to_plantuml()),
+                "state" => println!("{}", p.state_machines().to_plantuml()),
+                "sequence" => {
+                    let sequence = SequenceDiagram {
+                        classes: p.classes(),
+                        entry: matches.value_of("function").unwrap().to_owned(),
+                        depth: matches.value_of("depth").unwrap().parse().unwrap_or(3),
+                    };
+                    println!("{}", sequence.to_plantuml());
+                }
+                _ => println!("{}", p.to_plantuml()),
+            }
         }
     }
-    println!("{}", p.to_plantuml());
 }
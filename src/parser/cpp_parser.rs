@@ -1,35 +1,24 @@
+use crate::parser::{
+    add_module, add_module_dependency, descendants_of_kind, field, fields, one_line,
+};
 use crate::uml::*;
 use tree_sitter::{Node, Query, QueryCursor};
 
 pub const METHOD_ARGS_QUERY: &str = "
-[(parameter_declaration
-  type: (_)@function.parameter.type
-  declarator: (_)@function.parameter.name
-  )
-(optional_parameter_declaration
-  type: (_)@function.parameter.type
-  declarator: (_)@function.parameter.name
-  )]
+[(parameter_declaration)
+(optional_parameter_declaration)] @function.parameter
 ";
 pub const METHOD_QUERY: &str = "
 [
-(declaration
-  declarator: (function_declarator
-    declarator: (_)@function.name
-    parameters: (_)@function.parameters
-))
-(field_declaration type: (_) @function.return_type
-  declarator: (function_declarator
-    declarator: (_)@function.name
-    parameters: (_)@function.parameters
-    ))
-]
+(declaration declarator: (_))
+(field_declaration declarator: (_))
+(function_definition declarator: (_))
+] @function
 ";
 pub const CLASS_FIELDS_QUERY: &str = "
 (field_declaration
-  type: (primitive_type) @class.field.type
-  [(field_identifier)(array_declarator)] @class.field.name
-)
+  type: (_) @class.field.type
+) @class.field
 ";
 
 pub const CLASS_ASSOCIATIONS_QUERY: &str = "
@@ -46,9 +35,15 @@ pub const CLASS_EXTENDS_QUERY: &str = "
 )
 ";
 pub const CLASS_QUERY: &str = "
-(class_specifier
+[(class_specifier
+  name: (_)@class.name
+  body: (field_declaration_list) @class.fields)
+(struct_specifier
   name: (_)@class.name
-  body: (field_declaration_list) @class.fields) @class
+  body: (field_declaration_list) @class.fields)
+(union_specifier
+  name: (_)@class.name
+  body: (field_declaration_list) @class.fields)] @class
 ";
 
 pub struct CppParser {
@@ -56,6 +51,13 @@ pub struct CppParser {
     classes: Vec<UmlClass>,
     enums: Vec<UmlEnum>,
     associations: Vec<UmlAssociation>,
+    blanket_impls: Vec<UmlBlanketImpl>,
+    modules: Vec<UmlModule>,
+    /// Path of the parsed unit relative to the root, `net/socket.h`.
+    module: String,
+    /// Includes of each unit as written, resolved against the parsed units in `finish`.
+    includes: Vec<(String, String)>,
+    functions: Vec<UmlFfiFunction>,
     method_query: Query,
     method_args_query: Query,
     class_query: Query,
@@ -71,6 +73,23 @@ impl crate::parser::LangParser for CppParser {
         self.classes
             .append(self.parse_classes(tree.root_node(), buffer).as_mut());
         // self.classes = self.parse_classes(tree.root_node(), buffer);
+        self.enums
+            .append(self.parse_enums(tree.root_node(), buffer).as_mut());
+        for function in self.parse_functions(tree.root_node(), buffer) {
+            if !self.functions.iter().any(|x| x.name == function.name) {
+                self.functions.push(function);
+            }
+        }
+        if !self.module.is_empty() {
+            let module = UmlModule {
+                name: self.module.clone(),
+                dependencies: vec![],
+            };
+            add_module(&mut self.modules, module);
+            for include in self.parse_includes(tree.root_node(), buffer) {
+                self.includes.push((self.module.clone(), include));
+            }
+        }
     }
 
     fn classes(&self) -> &Vec<UmlClass> {
@@ -84,6 +103,89 @@ impl crate::parser::LangParser for CppParser {
     fn assocations(&self) -> &Vec<UmlAssociation> {
         &self.associations
     }
+
+    fn blanket_impls(&self) -> &Vec<UmlBlanketImpl> {
+        &self.blanket_impls
+    }
+
+    fn parse_module(&mut self, module: &str, buffer: &Vec<u8>) {
+        self.module = module.to_owned();
+        self.parse(buffer);
+    }
+
+    fn modules(&self) -> &Vec<UmlModule> {
+        &self.modules
+    }
+
+    fn finish(&mut self) {
+        // bases and member types may be declared in any of the files
+        self.resolve_types();
+        let units: Vec<String> = self.modules.iter().map(|x| x.name.clone()).collect();
+        for (module, include) in self.includes.drain(..) {
+            let to = resolve_include(&units, &module, &include);
+            if to != module {
+                let existing = self.modules.iter_mut().find(|x| x.name == module);
+                add_module_dependency(&mut existing.unwrap().dependencies, &to, 1);
+            }
+        }
+    }
+
+    fn parse_activity(&mut self, buffer: &Vec<u8>, path: &str) -> Vec<UmlActivityDiagram> {
+        let mut v = vec![];
+        let tree = match self.ts_parser.parse(buffer, None) {
+            Some(tree) => tree,
+            None => return v,
+        };
+        // `Device::join(Game *)` picks one of the overloads of `Device::join`
+        let (path, parameters) = match path.find('(') {
+            Some(i) => (&path[..i], Some(&path[i..])),
+            None => (path, None),
+        };
+        let spaceless = |x: &str| x.replace(char::is_whitespace, "");
+        for function in descendants_of_kind(tree.root_node(), &["function_definition"]) {
+            let declarator = field(function, "declarator");
+            let declarator = match descendants_of_kind(declarator, &["function_declarator"]).first()
+            {
+                Some(x) => *x,
+                None => continue,
+            };
+            // prefix the name with the classes and namespaces the definition is nested in
+            let mut name = field(declarator, "declarator")
+                .utf8_text(buffer)
+                .unwrap()
+                .to_owned();
+            let mut parent = function.parent();
+            while let Some(p) = parent {
+                match p.kind() {
+                    "class_specifier" | "struct_specifier" | "namespace_definition" => {
+                        if let Some(owner) = p.child_by_field_name("name") {
+                            name = format!("{}::{}", owner.utf8_text(buffer).unwrap(), name);
+                        }
+                    }
+                    _ => {}
+                }
+                parent = p.parent();
+            }
+            if name != path && !name.ends_with(&format!("::{}", path)) {
+                continue;
+            }
+            let types: Vec<String> = self
+                .parse_method_args(field(declarator, "parameters"), buffer)
+                .into_iter()
+                .map(|x| x.data_type)
+                .collect();
+            let signature = format!("({})", types.join(", "));
+            match parameters {
+                Some(x) if spaceless(x) != spaceless(&signature) => continue,
+                _ => {}
+            }
+            v.push(UmlActivityDiagram {
+                name: format!("{}{}", path, signature),
+                activities: self.parse_activities(field(function, "body"), buffer),
+            });
+        }
+        v
+    }
 }
 impl CppParser {
     pub fn new() -> Self {
@@ -103,8 +205,85 @@ impl CppParser {
             classes: vec![],
             enums: vec![],
             associations: vec![],
+            blanket_impls: vec![],
+            modules: vec![],
+            module: String::new(),
+            includes: vec![],
+            functions: vec![],
         }
     }
+    /// Free functions declared or defined in the parsed files.
+    pub fn functions(&self) -> &Vec<UmlFfiFunction> {
+        &self.functions
+    }
+    /// Free functions below `node`, with `C` linkage inside of `extern "C"` blocks.
+    fn parse_functions(&self, node: Node, buffer: &Vec<u8>) -> Vec<UmlFfiFunction> {
+        let mut v = vec![];
+        for declarator in descendants_of_kind(node, &["function_declarator"]) {
+            // `char *name(void)` nests the function declarator into pointer declarators
+            let mut pointers = String::new();
+            let mut outer = declarator.parent().unwrap();
+            while outer.kind() == "pointer_declarator" || outer.kind() == "reference_declarator" {
+                pointers.push(if outer.kind() == "pointer_declarator" {
+                    '*'
+                } else {
+                    '&'
+                });
+                outer = outer.parent().unwrap();
+            }
+            // methods are named by field identifiers or qualified names
+            let name = field(declarator, "declarator");
+            if !(outer.kind() == "declaration" || outer.kind() == "function_definition")
+                || name.kind() != "identifier"
+            {
+                continue;
+            }
+            // `.h` headers are shared with C unless they declare into a namespace
+            let mut abi = match self.module.ends_with(".c") || self.module.ends_with(".h") {
+                true => "C".to_owned(),
+                false => "C++".to_owned(),
+            };
+            let mut parent = outer.parent();
+            while let Some(p) = parent {
+                match p.kind() {
+                    "linkage_specification" => {
+                        abi = field(p, "value")
+                            .utf8_text(buffer)
+                            .unwrap()
+                            .trim_matches('"')
+                            .to_owned();
+                        break;
+                    }
+                    "namespace_definition" => abi = "C++".to_owned(),
+                    _ => {}
+                }
+                parent = p.parent();
+            }
+            let return_type = match outer.child_by_field_name("type") {
+                Some(x) => format!("{}{}", x.utf8_text(buffer).unwrap(), pointers),
+                None => continue,
+            };
+            v.push(UmlFfiFunction {
+                name: name.utf8_text(buffer).unwrap().to_owned(),
+                abi,
+                parameters: self.parse_method_args(field(declarator, "parameters"), buffer),
+                return_type: Some(return_type),
+            });
+        }
+        v
+    }
+    /// The paths of the quoted includes, `#include <...>` names system headers.
+    fn parse_includes(&self, node: Node, buffer: &[u8]) -> Vec<String> {
+        let mut v = vec![];
+        for include in descendants_of_kind(node, &["preproc_include"]) {
+            let path = field(include, "path");
+            if path.kind() != "string_literal" {
+                continue;
+            }
+            v.push(path.utf8_text(buffer).unwrap().trim_matches('"').to_owned());
+        }
+        v
+    }
     pub fn parse_method_args(&self, node: Node, buffer: &Vec<u8>) -> Vec<UmlParameter> {
         let mut v = vec![];
 
@@ -117,11 +296,41 @@ impl CppParser {
                 let mut name: Option<String> = None;
                 m.captures.iter().for_each(|c| {
                     match self.method_args_query.capture_names()[c.index as usize].as_str() {
-                        "function.parameter.name" => {
-                            name = Some(c.node.utf8_text(&buffer).unwrap().to_owned())
+                        // parameters of function pointer parameters are not our own
+                        "function.parameter"
+                            if node.kind() == "parameter_list" && c.node.parent() != Some(node) => {
                         }
-                        "function.parameter.type" => {
-                            data_type = Some(c.node.utf8_text(&buffer).unwrap().to_owned())
+                        "function.parameter" => {
+                            // `Game *game` => `game : Game*`
+                            let (declarator, suffix) =
+                                match c.node.child_by_field_name("declarator") {
+                                    Some(declarator) => unwrap_declarator(declarator, buffer),
+                                    // `f(void)` declares no parameters
+                                    None if declaration_type(c.node, buffer) == "void" => return,
+                                    None => (c.node, String::new()),
+                                };
+                            let (declarator, suffix) = match declarator.kind() {
+                                // function pointers keep their full signature as the type
+                                "function_declarator" => (
+                                    unwrap_declarator(field(declarator, "declarator"), buffer).0,
+                                    one_line(c.node.utf8_text(buffer).unwrap()),
+                                ),
+                                "abstract_function_declarator" => {
+                                    (declarator, one_line(c.node.utf8_text(buffer).unwrap()))
+                                }
+                                _ => (
+                                    declarator,
+                                    format!("{}{}", declaration_type(c.node, buffer), suffix),
+                                ),
+                            };
+                            // `operator==(const Foo&)` leaves its parameter unnamed
+                            name = match declarator == c.node
+                                || declarator.kind().starts_with("abstract_")
+                            {
+                                true => Some(String::new()),
+                                false => Some(declarator.utf8_text(buffer).unwrap().to_owned()),
+                            };
+                            data_type = Some(suffix);
                         }
                         _ => {
                             panic!("{}", c.node.utf8_text(&buffer).unwrap().to_owned());
@@ -130,6 +339,7 @@ impl CppParser {
                 });
                 match (name, data_type) {
                     (Some(name), Some(data_type)) => v.push(UmlParameter { data_type, name }),
+                    (None, None) => {}
                     _ => {
                         panic!("{}", node.utf8_text(&buffer).unwrap().to_owned());
                     }
@@ -143,27 +353,86 @@ impl CppParser {
         for m in
             QueryCursor::new().matches(&self.method_query, node, |x| x.utf8_text(buffer).unwrap())
         {
-            let mut return_type: Option<String> = None;
-            let mut name: Option<String> = None;
-            let mut params: Vec<UmlParameter> = vec![];
+            let mut function: Option<Node> = None;
             for c in m.captures.iter() {
                 match self.method_query.capture_names()[c.index as usize].as_str() {
-                    "function.name" => name = Some(c.node.utf8_text(&buffer).unwrap().to_owned()),
-                    "function.return_type" => {
-                        return_type = Some(c.node.utf8_text(&buffer).unwrap().to_owned())
-                    }
-                    "function.parameters" => {
-                        params = self.parse_method_args(c.node, buffer);
-                    }
+                    "function" => function = Some(c.node),
                     _ => {}
                 }
             }
-            v.push(UmlMethod {
-                name: name.as_ref().unwrap().to_string(),
-                visibility: UmlVisibility::Public,
-                parameters: params,
-                return_type: return_type,
-            });
+            let function = function.unwrap();
+            if !is_direct_member(node, function) {
+                continue;
+            }
+            for declarator in fields(function, "declarator") {
+                // `Game *current();` returns a `Game*`
+                let (declarator, suffix) = unwrap_declarator(declarator, buffer);
+                let mut return_type = function
+                    .child_by_field_name("type")
+                    .map(|_| format!("{}{}", declaration_type(function, buffer), suffix));
+                let (name, kind) = match declarator.kind() {
+                    "function_declarator" => {
+                        let name = field(declarator, "declarator");
+                        // `Device::~Device` names the destructor in its last segment
+                        let mut last = name;
+                        let mut scope = None;
+                        while let Some(x) = last.child_by_field_name("name") {
+                            // 0.19 grammars name the scope of `Foo::Foo` its namespace
+                            scope = last
+                                .child_by_field_name("scope")
+                                .or_else(|| last.child_by_field_name("namespace"));
+                            last = x;
+                        }
+                        // the class named by the scope of a definition or declaring the member
+                        let class = match scope {
+                            Some(scope) => Some(scope.utf8_text(buffer).unwrap().to_owned()),
+                            None => enclosing_class(function, buffer),
+                        };
+                        let class = class.map(|x| x.split('<').next().unwrap().trim().to_owned());
+                        let text = last.utf8_text(buffer).unwrap();
+                        let kind = match (last.kind(), &return_type) {
+                            ("destructor_name", _) => UmlMethodKind::Destructor,
+                            // older grammars read `operator bool()` as an operator name
+                            ("operator_name", None) => UmlMethodKind::Conversion,
+                            ("operator_name", _) => UmlMethodKind::Operator,
+                            (_, None) if class.as_deref() == Some(text) => {
+                                UmlMethodKind::Constructor
+                            }
+                            _ => UmlMethodKind::Method,
+                        };
+                        if kind == UmlMethodKind::Conversion {
+                            let data_type = text.trim_start_matches("operator").trim();
+                            return_type = Some(one_line(data_type));
+                        }
+                        (one_line(name.utf8_text(buffer).unwrap()), kind)
+                    }
+                    // `operator bool() const` returns its type
+                    "operator_cast" => {
+                        let data_type = declaration_type(declarator, buffer);
+                        // `operator void*()` wraps its parameters in a pointer declarator
+                        let (_, suffix) =
+                            unwrap_declarator(field(declarator, "declarator"), buffer);
+                        let data_type = format!("{}{}", data_type, suffix);
+                        return_type = Some(data_type.clone());
+                        (format!("operator {}", data_type), UmlMethodKind::Conversion)
+                    }
+                    _ => continue,
+                };
+                let declarator = match declarator.kind() {
+                    "operator_cast" => unwrap_declarator(field(declarator, "declarator"), buffer).0,
+                    _ => declarator,
+                };
+                v.push(UmlMethod {
+                    name,
+                    kind,
+                    visibility: member_visibility(function, buffer),
+                    parameters: self.parse_method_args(field(declarator, "parameters"), buffer),
+                    return_type,
+                    calls: vec![],
+                    template_parameters: template_parameters(function, buffer),
+                    modifiers: member_modifiers(function, declarator, buffer),
+                });
+            }
         }
         v
     }
@@ -173,6 +442,10 @@ impl CppParser {
         for m in QueryCursor::new().matches(&self.class_associations_query, node, |x| {
             x.utf8_text(buffer).unwrap()
         }) {
+            if !is_direct_member(node, m.captures[0].node) {
+                continue;
+            }
+            let declaration = m.captures[0].node.parent().unwrap();
             let mut to: Option<String> = None;
             let mut to_title: Option<String> = None;
             for c in m.captures.iter() {
@@ -180,8 +453,10 @@ impl CppParser {
                     "class.association.to" => {
                         to = Some(c.node.utf8_text(buffer).unwrap().to_owned())
                     }
+                    // `Peer peers[4];` is titled `peers`, the size goes with the type
                     "class.association.to_title" => {
-                        to_title = Some(c.node.utf8_text(buffer).unwrap().to_owned())
+                        let (name, _) = unwrap_declarator(c.node, buffer);
+                        to_title = Some(name.utf8_text(buffer).unwrap().to_owned())
                     }
                     _ => panic!("{}", c.node.utf8_text(buffer).unwrap().to_owned()),
                 }
@@ -190,6 +465,9 @@ impl CppParser {
                 from_title: to_title.as_ref().unwrap().to_string(),
                 to: to.as_ref().unwrap().to_string(),
                 to_title: to_title.as_ref().unwrap().to_string(),
+                polymorphic: false,
+                is_static: member_modifiers(declaration, declaration, buffer)
+                    .contains(&UmlMemberModifier::Static),
             });
         }
         v
@@ -199,42 +477,167 @@ impl CppParser {
         for m in QueryCursor::new().matches(&self.class_fields_query, node, |x| {
             x.utf8_text(buffer).unwrap()
         }) {
-            let mut data_type: Option<String> = None;
-            let mut name: Option<String> = None;
+            if !is_direct_member(node, m.captures[0].node) {
+                continue;
+            }
+            let mut declaration: Option<Node> = None;
             for c in m.captures.iter() {
                 match self.class_fields_query.capture_names()[c.index as usize].as_str() {
-                    "class.field.name" => name = Some(c.node.utf8_text(buffer).unwrap().to_owned()),
-                    "class.field.type" => {
-                        data_type = Some(c.node.utf8_text(buffer).unwrap().to_owned())
-                    }
-                    _ => {} // _ => panic!("{}", c.node.utf8_text(buffer).unwrap().to_owned()),
+                    "class.field" => declaration = Some(c.node),
+                    _ => {}
                 }
             }
-            v.push(UmlField {
-                name: name.as_ref().unwrap().to_string(),
-                data_type: data_type.as_ref().unwrap().to_string(),
-                visibility: UmlVisibility::Public,
-            });
+            let declaration = declaration.unwrap();
+            // `char host_ip[15], port[6];` declares two fields
+            for declarator in fields(declaration, "declarator") {
+                let (name, suffix) = unwrap_declarator(declarator, buffer);
+                // methods are field declarations too
+                if name.kind() == "function_declarator" {
+                    continue;
+                }
+                v.push(UmlField {
+                    name: name.utf8_text(buffer).unwrap().to_owned(),
+                    data_type: format!("{}{}", declaration_type(declaration, buffer), suffix),
+                    visibility: member_visibility(declaration, buffer),
+                    modifiers: member_modifiers(declaration, name, buffer),
+                });
+            }
         }
         v
     }
-    fn parse_extensions(&self, node: Node, buffer: &Vec<u8>) -> Vec<String> {
+    /// Base classes of the class `node` with their access and `virtual` keyword,
+    /// `: protected virtual io::Stream`.
+    fn parse_extensions(&self, node: Node, buffer: &[u8]) -> Vec<UmlGeneralization> {
         let mut v = vec![];
-        let query = &Query::new(
-            self.ts_parser.language().unwrap(),
-            "(base_class_clause (type_identifier) @class.extends)",
-        )
-        .unwrap();
-        for m in QueryCursor::new().matches(query, node, |x| x.utf8_text(buffer).unwrap()) {
-            for c in m.captures.iter() {
-                match query.capture_names()[c.index as usize].as_str() {
-                    "class.extends" => v.push(c.node.utf8_text(buffer).unwrap().to_owned()),
-                    _ => {} // panic!("{}", c.node.utf8_text(buffer).unwrap().to_owned()),
-                }
+        // bases of structs and unions are public unless specified
+        let default_access = || match node.kind() {
+            "class_specifier" => UmlVisibility::Private,
+            _ => UmlVisibility::Public,
+        };
+        // older grammars do not know `virtual` bases and leave parts of the clause in errors
+        // following it, `public virtual Device` parses as the base `virtual` and an error
+        let mut tokens = vec![];
+        for i in 0..node.child_count() {
+            let child = node.child(i).unwrap();
+            match child.kind() {
+                "base_class_clause" => {}
+                "ERROR" if !tokens.is_empty() => {}
+                _ if tokens.is_empty() => continue,
+                _ => break,
+            }
+            for j in 0..child.child_count() {
+                tokens.push(child.child(j).unwrap());
             }
         }
+        let mut access = None;
+        let mut is_virtual = false;
+        for token in tokens {
+            match token.utf8_text(buffer).unwrap().trim() {
+                "public" => access = Some(UmlVisibility::Public),
+                "protected" => access = Some(UmlVisibility::Protected),
+                "private" => access = Some(UmlVisibility::Private),
+                "virtual" => is_virtual = true,
+                name => match token.kind() {
+                    // `io::Stream` is a scoped or qualified identifier depending on
+                    // the grammar version
+                    "type_identifier"
+                    | "identifier"
+                    | "scoped_type_identifier"
+                    | "qualified_identifier"
+                    | "template_type" => {
+                        v.push(UmlGeneralization {
+                            name: one_line(name),
+                            access: access.take().unwrap_or_else(default_access),
+                            is_virtual,
+                        });
+                        is_virtual = false;
+                    }
+                    _ => {}
+                },
+            }
+        }
+        v
+    }
+    /// Control flow of the statements in `block`.
+    fn parse_activities(&self, block: Node, buffer: &Vec<u8>) -> Vec<UmlActivity> {
+        let mut v = vec![];
+        for i in 0..block.named_child_count() {
+            v.append(&mut self.parse_activity_statement(block.named_child(i).unwrap(), buffer));
+        }
         v
     }
+    fn parse_activity_statement(&self, node: Node, buffer: &Vec<u8>) -> Vec<UmlActivity> {
+        let text = |x: Node| one_line(x.utf8_text(buffer).unwrap());
+        match node.kind() {
+            "comment" => vec![],
+            "compound_statement" => self.parse_activities(node, buffer),
+            "if_statement" => {
+                let otherwise = match node.child_by_field_name("alternative") {
+                    // newer grammars wrap the alternative into an `else_clause`
+                    Some(x) if x.kind() == "else_clause" => {
+                        self.parse_activity_statement(x.named_child(0).unwrap(), buffer)
+                    }
+                    Some(x) => self.parse_activity_statement(x, buffer),
+                    None => vec![],
+                };
+                vec![UmlActivity::Decision {
+                    condition: condition_text(field(node, "condition"), buffer),
+                    then: self.parse_activity_statement(field(node, "consequence"), buffer),
+                    otherwise,
+                }]
+            }
+            "switch_statement" => {
+                let mut cases = vec![];
+                for case in descendants_of_kind(field(node, "body"), &["case_statement"]) {
+                    if case.parent() != node.child_by_field_name("body") {
+                        continue;
+                    }
+                    let value = case.child_by_field_name("value");
+                    let mut activities = vec![];
+                    for i in 0..case.named_child_count() {
+                        let statement = case.named_child(i).unwrap();
+                        if Some(statement) != value && statement.kind() != "break_statement" {
+                            activities
+                                .append(&mut self.parse_activity_statement(statement, buffer));
+                        }
+                    }
+                    cases.push(UmlActivityCase {
+                        pattern: value.map_or("default".to_owned(), text),
+                        activities,
+                    });
+                }
+                vec![UmlActivity::Switch {
+                    subject: condition_text(field(node, "condition"), buffer),
+                    cases,
+                }]
+            }
+            "while_statement" => vec![UmlActivity::Loop {
+                condition: condition_text(field(node, "condition"), buffer),
+                body: self.parse_activity_statement(field(node, "body"), buffer),
+            }],
+            "do_statement" => vec![UmlActivity::Repeat {
+                condition: condition_text(field(node, "condition"), buffer),
+                body: self.parse_activity_statement(field(node, "body"), buffer),
+            }],
+            "for_statement" | "for_range_loop" => {
+                // the body is the last statement, everything before it is the loop header
+                let body = node.named_child(node.named_child_count() - 1).unwrap();
+                let header = &buffer[node.start_byte()..body.start_byte()];
+                vec![UmlActivity::Loop {
+                    condition: one_line(std::str::from_utf8(header).unwrap()),
+                    body: self.parse_activity_statement(body, buffer),
+                }]
+            }
+            "return_statement" | "throw_statement" => {
+                vec![UmlActivity::Return(
+                    text(node).trim_end_matches(';').to_owned(),
+                )]
+            }
+            _ => vec![UmlActivity::Action(
+                text(node).trim_end_matches(';').to_owned(),
+            )],
+        }
+    }
     pub fn parse_classes(&self, node: Node, buffer: &Vec<u8>) -> Vec<UmlClass> {
         // struct + impl => class
         // classes
@@ -247,35 +650,570 @@ impl CppParser {
             let mut fields: Vec<UmlField> = vec![];
             let mut methods: Vec<UmlMethod> = vec![];
             let mut associations: Vec<UmlAssociation> = vec![];
-            let mut extends: Vec<String> = vec![];
+            let mut extends: Vec<UmlGeneralization> = vec![];
             let mut implements: Vec<String> = vec![];
+            let mut owner: Option<String> = None;
+            let mut package: Option<String> = None;
+            let mut parameters: Vec<String> = vec![];
+            let mut specializes: Option<String> = None;
+            let mut visibility = UmlVisibility::Public;
             for c in m.captures.iter() {
                 match self.class_query.capture_names()[c.index as usize].as_str() {
-                    "class" => extends = self.parse_extensions(c.node, buffer),
+                    "class" => {
+                        extends = self.parse_extensions(c.node, buffer);
+                        owner = class_owner(c.node, buffer);
+                        package = class_namespace(c.node, buffer);
+                        parameters = template_parameters(c.node, buffer);
+                        visibility = member_visibility(c.node, buffer);
+                    }
                     "class.fields" => {
-                        // TODO get visibility here
                         fields = self.parse_class_fields(c.node, buffer);
                         methods = self.parse_methods(c.node, buffer);
                         associations = self.parse_associations(c.node, buffer);
                     }
-                    "class.name" => name = Some(c.node.utf8_text(buffer).unwrap().to_owned()),
+                    "class.name" => {
+                        // `template <> class Buffer<bool>` specializes `Buffer`
+                        if c.node.kind() == "template_type" {
+                            specializes =
+                                Some(field(c.node, "name").utf8_text(buffer).unwrap().to_owned());
+                        }
+                        name = Some(one_line(c.node.utf8_text(buffer).unwrap()));
+                    }
                     _ => {}
                 }
             }
+            let name = name.expect("No class name found");
+            // `= 0` makes a class abstract, an interface if it has nothing but those
+            let is_abstract = |x: &UmlMethod| x.modifiers.contains(&UmlMemberModifier::Abstract);
+            let modifier = match methods.iter().any(is_abstract) {
+                true if fields.is_empty()
+                    && methods
+                        .iter()
+                        .all(|x| is_abstract(x) || x.name.starts_with('~')) =>
+                {
+                    Some(UmlClassModifier::Interface)
+                }
+                true => Some(UmlClassModifier::Abstract),
+                false => None,
+            };
+            let scope = match (&owner, &package) {
+                (Some(scope), _) | (None, Some(scope)) => format!("{}::", scope),
+                (None, None) => String::new(),
+            };
+            // `T data[N];` of `template <typename T, int N>` refers to no class
+            associations.retain(|x| {
+                !parameters.iter().any(|parameter| {
+                    let declared = parameter.split('=').next().unwrap().trim_end();
+                    let declared = declared.rsplit(' ').next().unwrap();
+                    declared.trim_end_matches("...") == x.to
+                })
+            });
             classes.push(UmlClass {
-                name: name.expect("No class name found"),
+                name: format!("{}{}", scope, name),
                 fields: fields,
                 methods: methods,
-                modifier: None,
-                visibility: UmlVisibility::Public,
+                modifier,
+                visibility,
                 extends,
                 implements,
                 associations,
+                dependencies: vec![],
+                uses: vec![],
+                owner,
+                package,
+                template_parameters: parameters,
+                specializes: specializes.map(|x| format!("{}{}", scope, x)),
             })
         }
 
         classes
     }
+    /// Named enums below `node`, scoped or not, with the enumerators they declare.
+    pub fn parse_enums(&self, node: Node, buffer: &[u8]) -> Vec<UmlEnum> {
+        let mut enums = vec![];
+        let text = |x: Node| one_line(x.utf8_text(buffer).unwrap());
+        for specifier in descendants_of_kind(node, &["enum_specifier"]) {
+            let name = match specifier.child_by_field_name("name") {
+                Some(name) => text(name),
+                None => continue,
+            };
+            let mut variants = vec![];
+            let mut values = vec![];
+            match specifier.child_by_field_name("body") {
+                Some(body) => {
+                    for i in 0..body.named_child_count() {
+                        let enumerator = body.named_child(i).unwrap();
+                        if enumerator.kind() == "enumerator" {
+                            variants.push(text(field(enumerator, "name")));
+                            values.push(enumerator.child_by_field_name("value").map(text));
+                        }
+                    }
+                }
+                None => {
+                    // older grammars parse enums in classes as a field declaration initialized
+                    // with the enumerators, `enum Color { RED = 1 };`
+                    let declaration = specifier.parent().unwrap();
+                    let list = match declaration.child_by_field_name("default_value") {
+                        Some(list)
+                            if declaration.kind() == "field_declaration"
+                                && list.kind() == "initializer_list"
+                                && declaration.child_by_field_name("declarator").is_none() =>
+                        {
+                            list
+                        }
+                        // `enum Color tint;` only refers to the enum
+                        _ => continue,
+                    };
+                    for i in 0..list.named_child_count() {
+                        let enumerator = list.named_child(i).unwrap();
+                        match enumerator.kind() {
+                            "assignment_expression" => {
+                                variants.push(text(field(enumerator, "left")));
+                                values.push(Some(text(field(enumerator, "right"))));
+                            }
+                            _ => {
+                                variants.push(text(enumerator));
+                                values.push(None);
+                            }
+                        }
+                    }
+                }
+            }
+            let is_scoped = (0..specifier.child_count())
+                .map(|i| specifier.child(i).unwrap().kind())
+                .any(|x| x == "class" || x == "struct");
+            let owner = class_owner(specifier, buffer);
+            let package = class_namespace(specifier, buffer);
+            enums.push(UmlEnum {
+                name: match (&owner, &package) {
+                    (Some(scope), _) | (None, Some(scope)) => format!("{}::{}", scope, name),
+                    (None, None) => name,
+                },
+                variants,
+                methods: vec![],
+                implements: vec![],
+                uses: vec![],
+                values,
+                underlying_type: specifier.child_by_field_name("base").map(text),
+                is_scoped,
+                owner,
+                package,
+            });
+        }
+        enums
+    }
+    /// Qualifies the base classes and associated types of the classes of all files the way
+    /// C++ looks them up, from the enclosing scope outwards, `Stream` in `net::http` may be
+    /// `net::http::Stream`, `net::Stream` or `Stream`. Unknown types are kept as written.
+    /// Fields typed by an enum become associations to it, bases that are interfaces are moved
+    /// to the implemented ones.
+    fn resolve_types(&mut self) {
+        let enums: Vec<String> = self.enums.iter().map(|x| x.name.clone()).collect();
+        let mut names: Vec<String> = self.classes.iter().map(|x| x.name.clone()).collect();
+        names.extend(enums.iter().cloned());
+        for class in self.classes.iter_mut() {
+            let scope = match class.owner.as_ref().or(class.package.as_ref()) {
+                Some(scope) => scope.clone(),
+                None => String::new(),
+            };
+            for base in class.extends.iter_mut() {
+                if let Some(name) = resolve_name(&names, &scope, &base.name) {
+                    base.name = name;
+                }
+            }
+            // members see the types nested in the class itself
+            let scope = class.name.clone();
+            for association in class.associations.iter_mut() {
+                if let Some(name) = resolve_name(&names, &scope, &association.to) {
+                    association.to = name;
+                }
+            }
+            for field in &class.fields {
+                // `const Color`, `enum Color` and `Device::Color` name the same enum
+                let mut data_type = field.data_type.as_str();
+                for prefix in &["const ", "volatile ", "enum "] {
+                    data_type = data_type.strip_prefix(prefix).unwrap_or(data_type);
+                }
+                let to = match resolve_name(&enums, &scope, data_type) {
+                    Some(to) if enums.contains(&to) => to,
+                    _ => continue,
+                };
+                if !class.associations.iter().any(|x| x.to_title == field.name) {
+                    class.associations.push(UmlAssociation {
+                        to,
+                        from_title: field.name.clone(),
+                        to_title: field.name.clone(),
+                        polymorphic: false,
+                        is_static: field.modifiers.contains(&UmlMemberModifier::Static),
+                    });
+                }
+            }
+        }
+        let interfaces: Vec<String> = self
+            .classes
+            .iter()
+            .filter(|x| matches!(x.modifier, Some(UmlClassModifier::Interface)))
+            .map(|x| x.name.clone())
+            .collect();
+        for class in self.classes.iter_mut() {
+            let (implements, extends): (Vec<UmlGeneralization>, Vec<UmlGeneralization>) = class
+                .extends
+                .drain(..)
+                .partition(|x| interfaces.contains(&x.name));
+            class.extends = extends;
+            class
+                .implements
+                .extend(implements.into_iter().map(|x| x.name));
+        }
+    }
+}
+
+/// The name of the class or struct `node` is declared in, `Box<T>` for the members of a
+/// template.
+fn enclosing_class(node: Node, buffer: &[u8]) -> Option<String> {
+    let mut parent = node.parent();
+    while let Some(p) = parent {
+        match p.kind() {
+            "class_specifier" | "struct_specifier" | "union_specifier" => {
+                return p
+                    .child_by_field_name("name")
+                    .map(|x| x.utf8_text(buffer).unwrap().to_owned());
+            }
+            "function_definition" | "translation_unit" => return None,
+            _ => parent = p.parent(),
+        }
+    }
+    None
+}
+
+/// The unit `#include "path"` in `unit` refers to: a unit next to the including one, else
+/// one found below an include directory. Headers outside of the sources keep their path.
+fn resolve_include(units: &[String], unit: &str, path: &str) -> String {
+    let mut segments: Vec<&str> = unit.split('/').collect();
+    segments.pop();
+    for segment in path.split('/') {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            s => segments.push(s),
+        }
+    }
+    let relative = segments.join("/");
+    if units.contains(&relative) {
+        return relative;
+    }
+    let suffix = format!("/{}", path);
+    match units.iter().find(|x| **x == path || x.ends_with(&suffix)) {
+        Some(x) => x.clone(),
+        None => path.to_owned(),
+    }
+}
+
+/// The element of `names` that `name` refers to from within `scope`, trying the enclosing
+/// scopes from the innermost outwards, `::Stream` names the global `Stream`.
+fn resolve_name(names: &[String], scope: &str, name: &str) -> Option<String> {
+    if let Some(global) = name.strip_prefix("::") {
+        return Some(global.to_owned());
+    }
+    let mut scope = scope;
+    loop {
+        let candidate = match scope.is_empty() {
+            true => name.to_owned(),
+            false => format!("{}::{}", scope, name),
+        };
+        if names.contains(&candidate) {
+            return Some(candidate);
+        }
+        if scope.is_empty() {
+            return None;
+        }
+        scope = match scope.rfind("::") {
+            Some(i) => &scope[..i],
+            None => "",
+        };
+    }
+}
+
+/// Specifiers and qualifiers of the member `declaration` declared by `declarator`, the
+/// unwrapped function declarator of member functions, `static`, `virtual`, `= 0`, `const`,
+/// `override` and the like.
+fn member_modifiers(declaration: Node, declarator: Node, buffer: &[u8]) -> Vec<UmlMemberModifier> {
+    let mut v = vec![];
+    for i in 0..declaration.named_child_count() {
+        let child = declaration.named_child(i).unwrap();
+        let text = child.utf8_text(buffer).unwrap();
+        match (child.kind(), text) {
+            ("virtual_function_specifier", _) => v.push(UmlMemberModifier::Virtual),
+            ("storage_class_specifier", "static") => v.push(UmlMemberModifier::Static),
+            ("storage_class_specifier", "inline") => v.push(UmlMemberModifier::Inline),
+            // older grammars parse `constexpr` as a type qualifier
+            ("type_qualifier", "constexpr") => v.push(UmlMemberModifier::Constexpr),
+            ("explicit_function_specifier", _) => v.push(UmlMemberModifier::Explicit),
+            // newer grammars parse `= 0` as a clause of its own
+            ("pure_virtual_clause", _) => v.push(UmlMemberModifier::Abstract),
+            ("default_method_clause", _) => v.push(UmlMemberModifier::Default),
+            ("delete_method_clause", _) => v.push(UmlMemberModifier::Delete),
+            _ => {}
+        }
+    }
+    // conversion operators are declared by abstract function declarators
+    if !declarator.kind().ends_with("function_declarator") {
+        return v;
+    }
+    if let Some(value) = declaration.child_by_field_name("default_value") {
+        if value.utf8_text(buffer).unwrap() == "0" {
+            v.push(UmlMemberModifier::Abstract);
+        }
+    }
+    for i in 0..declarator.named_child_count() {
+        let child = declarator.named_child(i).unwrap();
+        match (child.kind(), child.utf8_text(buffer).unwrap()) {
+            ("type_qualifier", "const") => v.push(UmlMemberModifier::Const),
+            ("noexcept", _) => v.push(UmlMemberModifier::Noexcept),
+            ("virtual_specifier", "override") => v.push(UmlMemberModifier::Override),
+            ("virtual_specifier", "final") => v.push(UmlMemberModifier::Final),
+            _ => {}
+        }
+    }
+    v
+}
+
+/// Splits a declarator into the node naming the declared entity and the type suffix the
+/// declarator adds, `*game` => (`game`, `*`), `host_ip[15]` => (`host_ip`, `[15]`).
+fn unwrap_declarator<'a>(declarator: Node<'a>, buffer: &[u8]) -> (Node<'a>, String) {
+    let mut node = declarator;
+    let mut pointers = String::new();
+    let mut arrays = String::new();
+    loop {
+        match node.kind() {
+            "pointer_declarator" => {
+                pointers.push('*');
+                // `int *const p`
+                for i in 0..node.named_child_count() {
+                    let child = node.named_child(i).unwrap();
+                    if child.kind() == "type_qualifier" {
+                        pointers.push_str(&format!(" {} ", child.utf8_text(buffer).unwrap()));
+                    }
+                }
+                node = field(node, "declarator");
+            }
+            "reference_declarator" => {
+                // `&` or `&&` precede the declarator
+                pointers.push_str(node.child(0).unwrap().utf8_text(buffer).unwrap());
+                node = node.named_child(node.named_child_count() - 1).unwrap();
+            }
+            "array_declarator" => {
+                let size = match node.child_by_field_name("size") {
+                    Some(size) => size.utf8_text(buffer).unwrap(),
+                    None => "",
+                };
+                arrays.insert_str(0, &format!("[{}]", size));
+                node = field(node, "declarator");
+            }
+            // unnamed parameters, `const Foo&`, end in an abstract declarator
+            "abstract_pointer_declarator" => {
+                pointers.push('*');
+                match node.child_by_field_name("declarator") {
+                    Some(x) => node = x,
+                    None => break,
+                }
+            }
+            "abstract_reference_declarator" => {
+                pointers.push_str(node.child(0).unwrap().utf8_text(buffer).unwrap());
+                match node.named_child_count() {
+                    0 => break,
+                    n => node = node.named_child(n - 1).unwrap(),
+                }
+            }
+            "init_declarator" => node = field(node, "declarator"),
+            "parenthesized_declarator" => node = node.named_child(0).unwrap(),
+            _ => break,
+        }
+    }
+    (node, format!("{}{}", pointers.trim_end(), arrays))
+}
+
+/// The type of a declaration with its `const` and `volatile` qualifiers, `const std::string`.
+fn declaration_type(declaration: Node, buffer: &[u8]) -> String {
+    let mut v = vec![];
+    for i in 0..declaration.named_child_count() {
+        let child = declaration.named_child(i).unwrap();
+        // `constexpr` and `mutable` are type qualifiers to older grammars
+        match (child.kind(), child.utf8_text(buffer).unwrap()) {
+            ("type_qualifier", "const") | ("type_qualifier", "volatile") => {
+                v.push(child.utf8_text(buffer).unwrap().to_owned())
+            }
+            _ => {}
+        }
+    }
+    let data_type = field(declaration, "type");
+    v.push(match data_type.child_by_field_name("body") {
+        // `struct { int x; } point;` is named by its type only
+        Some(_) => match data_type.child_by_field_name("name") {
+            Some(name) => name.utf8_text(buffer).unwrap().to_owned(),
+            None => data_type.kind().trim_end_matches("_specifier").to_owned(),
+        },
+        None => one_line(data_type.utf8_text(buffer).unwrap()),
+    });
+    v.join(" ")
+}
+
+/// Whether the member captured at `member` is declared directly in `node`, and not in a class
+/// nested into it, when `node` is the body of a class.
+fn is_direct_member(node: Node, member: Node) -> bool {
+    let mut parent = member.parent();
+    while let Some(p) = parent {
+        if p == node {
+            return true;
+        }
+        match p.kind() {
+            // local declarations in function bodies and friends are no members
+            "compound_statement" | "friend_declaration" => return false,
+            "field_declaration_list" if node.kind() == "field_declaration_list" => return false,
+            _ => {}
+        }
+        parent = p.parent();
+    }
+    false
+}
+
+/// Parameters of the template declaring `node`, `typename T` => `T`, `typename... Ts` =>
+/// `Ts...`, non-type parameters as written, `int N = 4`.
+fn template_parameters(node: Node, buffer: &[u8]) -> Vec<String> {
+    let mut v = vec![];
+    let parameters = match node.parent() {
+        Some(p) if p.kind() == "template_declaration" => field(p, "parameters"),
+        _ => return v,
+    };
+    for i in 0..parameters.named_child_count() {
+        let parameter = parameters.named_child(i).unwrap();
+        let text = |x: Node| one_line(x.utf8_text(buffer).unwrap());
+        // `template <typename, typename = void>` leaves the parameters unnamed
+        let name = match parameter.kind() {
+            "optional_type_parameter_declaration" => parameter.child_by_field_name("name"),
+            _ => parameter.named_child(0),
+        }
+        .map(text);
+        v.push(match (parameter.kind(), name) {
+            ("type_parameter_declaration", Some(name)) => name,
+            ("variadic_type_parameter_declaration", Some(name)) => format!("{}...", name),
+            ("optional_type_parameter_declaration", name) => format!(
+                "{}= {}",
+                name.map(|x| x + " ").unwrap_or_default(),
+                text(field(parameter, "default_type"))
+            ),
+            _ => text(parameter),
+        });
+    }
+    v
+}
+
+/// Qualified name of the namespace `node` is declared in, `net::http`. Anonymous namespaces
+/// are named `anonymous`.
+fn class_namespace(node: Node, buffer: &[u8]) -> Option<String> {
+    let mut namespaces = vec![];
+    let mut parent = node.parent();
+    while let Some(p) = parent {
+        if p.kind() == "namespace_definition" {
+            // C++17 `namespace net::http { }` names both namespaces at once, older grammars
+            // read `net::` as an error before the name `http`
+            let mut names = vec![];
+            for i in 0..p.named_child_count() {
+                let child = p.named_child(i).unwrap();
+                match child.kind() {
+                    "ERROR" => {
+                        for j in 0..child.named_child_count() {
+                            let x = child.named_child(j).unwrap();
+                            if x.kind() == "identifier" {
+                                names.push(x.utf8_text(buffer).unwrap());
+                            }
+                        }
+                    }
+                    _ if Some(child) == p.child_by_field_name("name") => {
+                        names.push(child.utf8_text(buffer).unwrap())
+                    }
+                    _ => {}
+                }
+            }
+            namespaces.insert(
+                0,
+                match names.is_empty() {
+                    true => "anonymous".to_owned(),
+                    false => names.join("::"),
+                },
+            );
+        }
+        parent = p.parent();
+    }
+    match namespaces.is_empty() {
+        true => None,
+        false => Some(namespaces.join("::")),
+    }
+}
+
+/// Qualified name of the class, struct or union `class` is nested in, `net::Outer::Inner`.
+fn class_owner(class: Node, buffer: &[u8]) -> Option<String> {
+    let mut owners = vec![];
+    let mut parent = class.parent();
+    while let Some(p) = parent {
+        match p.kind() {
+            "class_specifier" | "struct_specifier" | "union_specifier" => {
+                if let Some(name) = p.child_by_field_name("name") {
+                    owners.insert(0, name.utf8_text(buffer).unwrap());
+                }
+            }
+            _ => {}
+        }
+        parent = p.parent();
+    }
+    if owners.is_empty() {
+        return None;
+    }
+    match class_namespace(class, buffer) {
+        Some(namespace) => Some(format!("{}::{}", namespace, owners.join("::"))),
+        None => Some(owners.join("::")),
+    }
+}
+
+/// Visibility of the member declared around `node`, given by the last access specifier
+/// before it. Members of a `class` are private by default, those of `struct`s and `union`s public.
+fn member_visibility(node: Node, buffer: &[u8]) -> UmlVisibility {
+    let mut member = node;
+    while let Some(parent) = member.parent() {
+        if parent.kind() == "field_declaration_list" {
+            break;
+        }
+        member = parent;
+    }
+    let list = match member.parent() {
+        Some(list) => list,
+        None => return UmlVisibility::Public,
+    };
+    let mut sibling = member.prev_sibling();
+    while let Some(x) = sibling {
+        if x.kind() == "access_specifier" {
+            // older grammars include the colon
+            return match x.utf8_text(buffer).unwrap().trim_end_matches(':').trim() {
+                "private" => UmlVisibility::Private,
+                "protected" => UmlVisibility::Protected,
+                _ => UmlVisibility::Public,
+            };
+        }
+        sibling = x.prev_sibling();
+    }
+    match list.parent().map(|x| x.kind()) {
+        Some("class_specifier") => UmlVisibility::Private,
+        _ => UmlVisibility::Public,
+    }
+}
+
+/// Text of a `condition_clause` or `parenthesized_expression` without the parentheses.
+fn condition_text(node: Node, buffer: &[u8]) -> String {
+    let text = one_line(node.utf8_text(buffer).unwrap());
+    let text = text.strip_prefix('(').unwrap_or(&text);
+    text.strip_suffix(')').unwrap_or(text).trim().to_owned()
 }
 
 #[cfg(test)]
@@ -358,7 +1296,7 @@ class Device: X
         let classes = p.parse_classes(root_node, &source_code.as_bytes().to_vec());
 
         assert!(classes.get(0).unwrap().name == "Device");
-        assert!(classes.get(0).unwrap().extends.get(0).unwrap() == "X");
+        assert!(classes.get(0).unwrap().extends.get(0).unwrap().name == "X");
 
         assert!(classes.get(0).unwrap().associations.get(0).unwrap().to == "Display");
         assert!(
@@ -376,4 +1314,797 @@ class Device: X
         assert!(classes.get(0).unwrap().methods.get(0).unwrap().name == "Device");
         assert!(classes.get(0).unwrap().methods.get(1).unwrap().name == "ip_to_number");
     }
+
+    #[test]
+    fn test_cpp_parse_visibility() {
+        use crate::generator::plantuml::PlantUml;
+        let mut p = CppParser::new();
+        let source_code = "
+class Device
+{
+    int id;
+    void reset();
+public:
+    int port;
+    void connect();
+protected:
+    char flags;
+};
+struct Point
+{
+    int x;
+    int length();
+private:
+    int cache;
+};
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+        let buffer = source_code.as_bytes().to_vec();
+
+        let fields: Vec<String> = p
+            .parse_class_fields(root_node, &buffer)
+            .iter()
+            .map(|x| x.to_plantuml())
+            .collect();
+        assert!(
+            fields
+                == vec![
+                    "-id : int",
+                    "+port : int",
+                    "#flags : char",
+                    "+x : int",
+                    "-cache : int"
+                ]
+        );
+        let methods = p.parse_methods(root_node, &buffer);
+        assert!(matches!(methods[0].visibility, UmlVisibility::Private));
+        assert!(matches!(methods[1].visibility, UmlVisibility::Public));
+        assert!(matches!(methods[2].visibility, UmlVisibility::Public));
+    }
+
+    #[test]
+    fn test_cpp_parse_nested_classes() {
+        let mut p = CppParser::new();
+        let source_code = "
+struct Game {
+    int round;
+    void start();
+    class Board : Grid {
+        int size;
+    public:
+        union Cell {
+            char piece;
+        };
+    };
+};
+union Value {
+    int i;
+};
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+        let classes = p.parse_classes(root_node, &source_code.as_bytes().to_vec());
+
+        let names: Vec<&str> = classes.iter().map(|x| x.name.as_str()).collect();
+        assert!(names == vec!["Game", "Game::Board", "Game::Board::Cell", "Value"]);
+        let game = &classes[0];
+        assert!(game.owner.is_none() && game.extends.is_empty());
+        assert!(game.fields.len() == 1 && game.methods.len() == 1);
+        assert!(matches!(game.fields[0].visibility, UmlVisibility::Public));
+        let board = &classes[1];
+        assert!(board.owner.as_deref() == Some("Game"));
+        assert!(board.extends.len() == 1 && board.extends[0].name == "Grid");
+        assert!(board.fields.len() == 1 && board.fields[0].name == "size");
+        assert!(matches!(board.fields[0].visibility, UmlVisibility::Private));
+        assert!(classes[2].owner.as_deref() == Some("Game::Board"));
+        assert!(matches!(classes[2].visibility, UmlVisibility::Public));
+    }
+
+    #[test]
+    fn test_cpp_parse_field_types() {
+        let mut p = CppParser::new();
+        let source_code = "
+class Device
+{
+public:
+    std::string name;
+    const std::vector<Player*> players;
+    Game *game;
+    Display &display;
+    char host_ip[15], port[6];
+    int grid[3][3];
+    static Device instance;
+    Peer peers[4];
+    bool join(Game* game);
+};
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+        let buffer = source_code.as_bytes().to_vec();
+
+        let fields = p.parse_class_fields(root_node, &buffer);
+        let fields: Vec<(&str, &str)> = fields
+            .iter()
+            .map(|x| (x.name.as_str(), x.data_type.as_str()))
+            .collect();
+        assert!(
+            fields
+                == vec![
+                    ("name", "std::string"),
+                    ("players", "const std::vector<Player*>"),
+                    ("game", "Game*"),
+                    ("display", "Display&"),
+                    ("host_ip", "char[15]"),
+                    ("port", "char[6]"),
+                    ("grid", "int[3][3]"),
+                    ("instance", "Device"),
+                    ("peers", "Peer[4]"),
+                ]
+        );
+        let classes = p.parse_classes(root_node, &buffer);
+        assert!(classes[0].fields.len() == 9);
+        let associations: Vec<(&str, &str)> = classes[0]
+            .associations
+            .iter()
+            .map(|x| (x.to_title.as_str(), x.to.as_str()))
+            .collect();
+        assert!(associations == vec![("instance", "Device"), ("peers", "Peer")]);
+    }
+
+    #[test]
+    fn test_cpp_parse_namespaces() {
+        use crate::generator::plantuml::PlantUml;
+        let mut p = CppParser::new();
+        let source_code = "
+namespace io {
+class Stream {};
+}
+class Stream {};
+namespace net {
+namespace http {
+class Stream : public io::Stream {};
+class Server : public Stream {
+    class Session {};
+};
+}
+class Client : public ::Stream {};
+}
+namespace {
+class Helper {};
+}
+namespace a::b {
+class C {};
+}
+";
+        p.parse(&source_code.as_bytes().to_vec());
+        p.finish();
+
+        let classes: Vec<(&str, Option<&str>, Vec<&str>)> = p
+            .classes()
+            .iter()
+            .map(|x| {
+                (
+                    x.name.as_str(),
+                    x.package.as_deref(),
+                    x.extends.iter().map(|x| x.name.as_str()).collect(),
+                )
+            })
+            .collect();
+        assert!(
+            classes
+                == vec![
+                    ("io::Stream", Some("io"), vec![]),
+                    ("Stream", None, vec![]),
+                    ("net::http::Stream", Some("net::http"), vec!["io::Stream"]),
+                    (
+                        "net::http::Server",
+                        Some("net::http"),
+                        vec!["net::http::Stream"]
+                    ),
+                    ("net::http::Server::Session", Some("net::http"), vec![]),
+                    ("net::Client", Some("net"), vec!["Stream"]),
+                    ("anonymous::Helper", Some("anonymous"), vec![]),
+                    ("a::b::C", Some("a::b"), vec![]),
+                ]
+        );
+        assert!(p.classes()[4].owner.as_deref() == Some("net::http::Server"));
+        assert!(p
+            .to_plantuml()
+            .contains("package \"net::http\" {\nclass net::http::Stream\nclass net::http::Server"));
+    }
+
+    #[test]
+    fn test_cpp_parse_templates() {
+        use crate::generator::plantuml::PlantUml;
+        let mut p = CppParser::new();
+        let source_code = "
+namespace io {
+template <typename T, int N = 4, typename... Ts>
+class Buffer {
+public:
+    template <typename U>
+    void push(U value);
+    template <typename U, typename = void>
+    void put(U value);
+private:
+    T data[N];
+    Stream source;
+};
+template <>
+class Buffer<bool> {};
+template <typename T>
+class Buffer<T*> {};
+template <typename T, typename = void>
+class Check {};
+}
+";
+        p.parse(&source_code.as_bytes().to_vec());
+
+        let classes = p.classes();
+        assert!(classes[0].name == "io::Buffer");
+        assert!(classes[0].template_parameters == vec!["T", "int N = 4", "Ts..."]);
+        assert!(classes[0].specializes.is_none());
+        assert!(classes[0].methods[0].template_parameters == vec!["U"]);
+        assert!(classes[0].methods[1].template_parameters == vec!["U", "= void"]);
+        // a member typed by a template parameter is no association
+        let associations: Vec<&str> = classes[0]
+            .associations
+            .iter()
+            .map(|x| x.to.as_str())
+            .collect();
+        assert!(associations == vec!["Stream"]);
+        assert!(classes[1].name == "io::Buffer<bool>");
+        assert!(classes[1].template_parameters.is_empty());
+        assert!(classes[1].specializes.as_deref() == Some("io::Buffer"));
+        assert!(classes[2].name == "io::Buffer<T*>");
+        assert!(classes[2].template_parameters == vec!["T"]);
+        assert!(classes[3].name == "io::Check");
+        assert!(classes[3].template_parameters == vec!["T", "= void"]);
+
+        let plantuml = p.to_plantuml();
+        assert!(plantuml.contains("class io::Buffer<T, int N = 4, Ts...> {"));
+        assert!(plantuml.contains("+push<U>(value : U): void"));
+        assert!(plantuml.contains("\"io::Buffer<bool>\" ..> io::Buffer : <<specializes>>"));
+    }
+
+    #[test]
+    fn test_cpp_parse_virtual() {
+        use crate::generator::plantuml::PlantUml;
+        let mut p = CppParser::new();
+        let source_code = "
+class Drawable {
+public:
+    virtual ~Drawable() = default;
+    virtual void draw() const = 0;
+};
+class Shape : public Drawable {
+public:
+    virtual double area() const = 0;
+    virtual void scale(double factor) {}
+protected:
+    int id;
+};
+class Circle final : public Shape {
+    double area() const override;
+    void draw() const final {}
+};
+";
+        p.parse(&source_code.as_bytes().to_vec());
+        p.finish();
+
+        let classes = p.classes();
+        assert!(matches!(
+            classes[0].modifier,
+            Some(UmlClassModifier::Interface)
+        ));
+        assert!(matches!(
+            classes[1].modifier,
+            Some(UmlClassModifier::Abstract)
+        ));
+        assert!(classes[2].modifier.is_none());
+        assert!(
+            classes[1].methods[0].modifiers
+                == vec![
+                    UmlMemberModifier::Virtual,
+                    UmlMemberModifier::Abstract,
+                    UmlMemberModifier::Const
+                ]
+        );
+        assert!(classes[1].methods[1].modifiers == vec![UmlMemberModifier::Virtual]);
+        assert!(
+            classes[2].methods[0].modifiers
+                == vec![UmlMemberModifier::Const, UmlMemberModifier::Override]
+        );
+        assert!(
+            classes[2].methods[1].modifiers
+                == vec![UmlMemberModifier::Const, UmlMemberModifier::Final]
+        );
+        assert!(classes[1].extends.is_empty() && classes[1].implements == vec!["Drawable"]);
+        assert!(classes[2].extends.len() == 1 && classes[2].extends[0].name == "Shape");
+
+        let plantuml = p.to_plantuml();
+        assert!(plantuml.contains("interface Drawable {"));
+        assert!(plantuml.contains("abstract class Shape {"));
+        assert!(plantuml.contains("{abstract} +area() const: double"));
+        assert!(plantuml.contains("+scale(factor : double): void <<virtual>>"));
+        assert!(plantuml.contains("-area() const: double <<override>>"));
+        assert!(plantuml.contains("Shape ..|> Drawable"));
+    }
+
+    #[test]
+    fn test_cpp_parse_bases_across_files() {
+        let mut p = CppParser::new();
+        // the derived class is parsed before the file declaring its bases
+        let circle = "
+namespace gfx {
+class Circle : public Shape, public Drawable {};
+}
+";
+        let shapes = "
+namespace gfx {
+class Drawable {
+public:
+    virtual void draw() const = 0;
+};
+class Shape {
+    int id;
+};
+}
+";
+        p.parse_module("circle.cpp", &circle.as_bytes().to_vec());
+        p.parse_module("shapes.h", &shapes.as_bytes().to_vec());
+        p.finish();
+
+        let circle = p
+            .classes()
+            .iter()
+            .find(|x| x.name == "gfx::Circle")
+            .unwrap();
+        assert!(circle.extends.len() == 1 && circle.extends[0].name == "gfx::Shape");
+        assert!(circle.implements == vec!["gfx::Drawable"]);
+    }
+
+    #[test]
+    fn test_cpp_parse_member_modifiers() {
+        use crate::generator::plantuml::PlantUml;
+        let mut p = CppParser::new();
+        let source_code = "
+class Device {
+public:
+    static Display display;
+    static constexpr int max_players = 4;
+    mutable int hits;
+    explicit Device(int port);
+    Device() = default;
+    ~Device() noexcept;
+    void reset() = delete;
+    static void error(std::string s);
+    inline int port() const noexcept { return 1; }
+};
+";
+        p.parse(&source_code.as_bytes().to_vec());
+
+        let device = &p.classes()[0];
+        let fields: Vec<(&str, &str, &Vec<UmlMemberModifier>)> = device
+            .fields
+            .iter()
+            .map(|x| (x.name.as_str(), x.data_type.as_str(), &x.modifiers))
+            .collect();
+        assert!(
+            fields
+                == vec![
+                    ("display", "Display", &vec![UmlMemberModifier::Static]),
+                    (
+                        "max_players",
+                        "int",
+                        &vec![UmlMemberModifier::Static, UmlMemberModifier::Constexpr]
+                    ),
+                    ("hits", "int", &vec![]),
+                ]
+        );
+        let methods: Vec<(&str, &Vec<UmlMemberModifier>)> = device
+            .methods
+            .iter()
+            .map(|x| (x.name.as_str(), &x.modifiers))
+            .collect();
+        assert!(
+            methods
+                == vec![
+                    ("Device", &vec![UmlMemberModifier::Explicit]),
+                    ("Device", &vec![UmlMemberModifier::Default]),
+                    ("~Device", &vec![UmlMemberModifier::Noexcept]),
+                    ("reset", &vec![UmlMemberModifier::Delete]),
+                    ("error", &vec![UmlMemberModifier::Static]),
+                    (
+                        "port",
+                        &vec![
+                            UmlMemberModifier::Inline,
+                            UmlMemberModifier::Const,
+                            UmlMemberModifier::Noexcept
+                        ]
+                    ),
+                ]
+        );
+
+        let plantuml = p.to_plantuml();
+        assert!(plantuml.contains("{static} +display : Display"));
+        assert!(plantuml.contains("{static} +max_players : int <<constexpr>>"));
+        assert!(plantuml.contains("+Device(port : int) <<create, explicit>>"));
+        assert!(plantuml.contains("+Device() <<create, default>>"));
+        assert!(plantuml.contains("{static} +error(s : std::string): void"));
+        assert!(plantuml.contains("+port() const noexcept: int <<inline>>"));
+        assert!(plantuml.contains("Device --> Display : display <<static>>"));
+    }
+
+    #[test]
+    fn test_cpp_parse_special_members() {
+        use crate::generator::plantuml::PlantUml;
+        let mut p = CppParser::new();
+        let source_code = "
+class Foo {
+public:
+    Foo(const Foo&);
+    ~Foo();
+    bool operator==(const Foo &other) const;
+    Foo &operator=(Foo&&);
+    explicit operator bool() const;
+    int size();
+};
+Foo::~Foo() {}
+Foo::Foo(int size) {}
+Foo::operator bool() const { return true; }
+";
+        p.parse(&source_code.as_bytes().to_vec());
+
+        let methods: Vec<(&str, &UmlMethodKind)> = p.classes()[0]
+            .methods
+            .iter()
+            .map(|x| (x.name.as_str(), &x.kind))
+            .collect();
+        assert!(
+            methods
+                == vec![
+                    ("Foo", &UmlMethodKind::Constructor),
+                    ("~Foo", &UmlMethodKind::Destructor),
+                    ("operator==", &UmlMethodKind::Operator),
+                    ("operator=", &UmlMethodKind::Operator),
+                    ("operator bool", &UmlMethodKind::Conversion),
+                    ("size", &UmlMethodKind::Method),
+                ]
+        );
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let methods = p.parse_methods(tree.root_node(), &source_code.as_bytes().to_vec());
+        let methods: Vec<(&str, &UmlMethodKind, Option<&str>)> = methods
+            .iter()
+            .map(|x| (x.name.as_str(), &x.kind, x.return_type.as_deref()))
+            .collect();
+        // a conversion has no return type either, only the class name makes a constructor
+        assert!(
+            methods[6..]
+                == [
+                    ("Foo::~Foo", &UmlMethodKind::Destructor, None),
+                    ("Foo::Foo", &UmlMethodKind::Constructor, None),
+                    ("operator bool", &UmlMethodKind::Conversion, Some("bool")),
+                ]
+        );
+
+        let plantuml = p.to_plantuml();
+        assert!(plantuml.contains("+Foo(const Foo&) <<create>>"));
+        assert!(plantuml.contains("+~Foo() <<destroy>>"));
+        assert!(plantuml.contains("+operator==(other : const Foo&) const: bool"));
+        assert!(plantuml.contains("+operator=(Foo&&): Foo&"));
+        assert!(plantuml.contains("+operator bool() const: bool <<explicit>>"));
+    }
+
+    #[test]
+    fn test_cpp_parse_pointer_conversion() {
+        use crate::generator::plantuml::PlantUml;
+        let mut p = CppParser::new();
+        let source_code = "
+class Handle {
+public:
+    operator void*() const;
+    operator const char**();
+};
+";
+        p.parse(&source_code.as_bytes().to_vec());
+
+        let methods = &p.classes()[0].methods;
+        assert!(methods.len() == 2);
+        assert!(
+            methods[0].name == "operator void*" && methods[0].kind == UmlMethodKind::Conversion
+        );
+        assert!(methods[0].return_type.as_deref() == Some("void*"));
+        assert!(methods[0].modifiers == vec![UmlMemberModifier::Const]);
+        assert!(methods[1].return_type.as_deref() == Some("const char**"));
+
+        let plantuml = p.to_plantuml();
+        assert!(plantuml.contains("+operator void*() const: void*"));
+    }
+
+    #[test]
+    fn test_cpp_parse_enums() {
+        use crate::generator::plantuml::PlantUml;
+        let mut p = CppParser::new();
+        let source_code = "
+namespace net {
+enum class Mode : uint8_t { Idle, Busy = 4 };
+}
+class Device {
+public:
+    enum Color { RED = 1, GREEN };
+    Color color;
+    net::Mode mode;
+    enum Color tint;
+};
+enum { ANONYMOUS };
+";
+        p.parse(&source_code.as_bytes().to_vec());
+        p.finish();
+
+        let enums = p.enums();
+        assert!(enums.len() == 2);
+        assert!(enums[0].name == "net::Mode" && enums[0].variants == vec!["Idle", "Busy"]);
+        assert!(enums[0].values == vec![None, Some("4".to_owned())]);
+        assert!(enums[1].name == "Device::Color" && enums[1].variants == vec!["RED", "GREEN"]);
+        assert!(enums[1].values == vec![Some("1".to_owned()), None]);
+        let mode = &p.enums()[0];
+        assert!(mode.is_scoped && mode.underlying_type.as_deref() == Some("uint8_t"));
+        assert!(mode.package.as_deref() == Some("net") && mode.owner.is_none());
+        let color = &p.enums()[1];
+        assert!(!color.is_scoped && color.owner.as_deref() == Some("Device"));
+
+        let associations: Vec<(&str, &str)> = p.classes()[0]
+            .associations
+            .iter()
+            .map(|x| (x.to_title.as_str(), x.to.as_str()))
+            .collect();
+        assert!(
+            associations
+                == vec![
+                    ("color", "Device::Color"),
+                    ("mode", "net::Mode"),
+                    ("tint", "Device::Color"),
+                ]
+        );
+
+        let plantuml = p.to_plantuml();
+        assert!(plantuml.contains("package \"net\" {\nenum net::Mode\n}"));
+        assert!(plantuml.contains("enum net::Mode <<enum class : uint8_t>> {\nIdle\nBusy = 4"));
+        assert!(plantuml.contains("enum Device::Color {\nRED = 1\nGREEN"));
+        assert!(plantuml.contains("Device +-- Device::Color"));
+        assert!(plantuml.contains("Device --> net::Mode : mode"));
+    }
+
+    #[test]
+    fn test_cpp_parse_inheritance() {
+        use crate::generator::plantuml::PlantUml;
+        let mut p = CppParser::new();
+        let source_code = "
+class Device {};
+class Input : public virtual Device {};
+class Output : virtual public Device {};
+class Terminal : public Input, protected Output, Logger {};
+struct Console : Terminal {};
+";
+        p.parse(&source_code.as_bytes().to_vec());
+
+        let classes = p.classes();
+        let bases: Vec<(&str, &UmlVisibility, bool)> = classes[3]
+            .extends
+            .iter()
+            .map(|x| (x.name.as_str(), &x.access, x.is_virtual))
+            .collect();
+        assert!(matches!(
+            bases.as_slice(),
+            [
+                ("Input", UmlVisibility::Public, false),
+                ("Output", UmlVisibility::Protected, false),
+                ("Logger", UmlVisibility::Private, false),
+            ]
+        ));
+        assert!(classes[1].extends[0].is_virtual && classes[2].extends[0].is_virtual);
+        assert!(matches!(
+            classes[2].extends[0].access,
+            UmlVisibility::Public
+        ));
+        assert!(matches!(
+            classes[4].extends[0].access,
+            UmlVisibility::Public
+        ));
+
+        assert!(diamonds(classes) == vec![("Terminal".to_owned(), "Device".to_owned())]);
+        let plantuml = p.to_plantuml();
+        assert!(plantuml.contains("Input --|> Device : <<virtual>>"));
+        assert!(plantuml.contains("Terminal --|> Output : <<protected>>"));
+        assert!(plantuml.contains("Terminal --|> Logger : <<private>>"));
+        assert!(plantuml.contains("note top of Terminal : diamond inheritance of Device"));
+    }
+
+    #[test]
+    fn test_cpp_parse_declarators() {
+        let mut p = CppParser::new();
+        let source_code = "
+class Server
+{
+public:
+    Game *current();
+    const Player &player(int index) const;
+    bool join(Game *game, int &server_socket, char name[16], void (*callback)(int code));
+    void close(), reset();
+};
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+        let buffer = source_code.as_bytes().to_vec();
+
+        let methods = p.parse_methods(root_node, &buffer);
+        let names: Vec<&str> = methods.iter().map(|x| x.name.as_str()).collect();
+        assert!(names == vec!["current", "player", "join", "close", "reset"]);
+        assert!(methods[0].return_type.as_deref() == Some("Game*"));
+        assert!(methods[1].return_type.as_deref() == Some("const Player&"));
+        let params: Vec<(&str, &str)> = methods[2]
+            .parameters
+            .iter()
+            .map(|x| (x.name.as_str(), x.data_type.as_str()))
+            .collect();
+        assert!(
+            params
+                == vec![
+                    ("game", "Game*"),
+                    ("server_socket", "int&"),
+                    ("name", "char[16]"),
+                    ("callback", "void (*callback)(int code)"),
+                ]
+        );
+    }
+
+    #[test]
+    fn test_cpp_parse_activity() {
+        let mut p = CppParser::new();
+        let source_code = "
+bool Device::join(Game *game)
+{
+    if (game == nullptr) {
+        return false;
+    }
+    while (connected) {
+        poll();
+    }
+    do {
+        poll();
+    } while (pending());
+    switch (state) {
+    case 1:
+        start();
+        break;
+    default:
+        stop();
+    }
+    return true;
+}
+bool Device::join(Game *game, int slot) {}
+";
+        let buffer = source_code.as_bytes().to_vec();
+
+        let activity = p.parse_activity(&buffer, "Device::join(Game *)").remove(0);
+        assert!(activity.name == "Device::join(Game*)");
+        let a = &activity.activities;
+        match a.get(0).unwrap() {
+            UmlActivity::Decision {
+                condition, then, ..
+            } => {
+                assert!(condition == "game == nullptr");
+                assert!(
+                    matches!(then.get(0).unwrap(), UmlActivity::Return(x) if x == "return false")
+                );
+            }
+            _ => panic!("no decision"),
+        }
+        assert!(
+            matches!(a.get(1).unwrap(), UmlActivity::Loop { condition, .. } if condition == "connected")
+        );
+        assert!(
+            matches!(a.get(2).unwrap(), UmlActivity::Repeat { condition, .. } if condition == "pending()")
+        );
+        match a.get(3).unwrap() {
+            UmlActivity::Switch { subject, cases } => {
+                assert!(subject == "state");
+                assert!(cases.get(0).unwrap().pattern == "1");
+                assert!(
+                    matches!(cases.get(0).unwrap().activities.get(0).unwrap(), UmlActivity::Action(x) if x == "start()")
+                );
+                assert!(cases.get(0).unwrap().activities.get(1).is_none());
+                assert!(cases.get(1).unwrap().pattern == "default");
+            }
+            _ => panic!("no switch"),
+        }
+        assert!(matches!(a.get(4).unwrap(), UmlActivity::Return(_)));
+        // overloads are ambiguous without their parameters
+        assert!(p.parse_activity(&buffer, "join").len() == 2);
+        assert!(p.parse_activity(&buffer, "Device::join(Game*, int)").len() == 1);
+        assert!(p.parse_activity(&buffer, "Other::join").is_empty());
+    }
+
+    #[test]
+    fn test_cpp_parse_functions() {
+        let mut p = CppParser::new();
+        let source_code = "
+extern \"C\" {
+int c_init(const Config *cfg);
+char *c_name(void);
+}
+void log(int level) {}
+namespace util {
+void trace(const char *text);
+}
+typedef void (*callback)(int);
+class Client {
+public:
+    bool join(Game* game);
+};
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let buffer = source_code.as_bytes().to_vec();
+        let functions = p.parse_functions(tree.root_node(), &buffer);
+
+        let functions: Vec<(&str, &str, &str)> = functions
+            .iter()
+            .map(|x| {
+                (
+                    x.name.as_str(),
+                    x.abi.as_str(),
+                    x.return_type.as_deref().unwrap(),
+                )
+            })
+            .collect();
+        assert!(
+            functions
+                == vec![
+                    ("c_init", "C", "int"),
+                    ("c_name", "C", "char*"),
+                    ("log", "C++", "void"),
+                    ("trace", "C++", "void"),
+                ]
+        );
+        // headers are shared with C
+        p.module = "api.h".to_owned();
+        let functions = p.parse_functions(tree.root_node(), &buffer);
+        let abis: Vec<&str> = functions.iter().map(|x| x.abi.as_str()).collect();
+        assert!(abis == vec!["C", "C", "C", "C++"]);
+    }
+
+    #[test]
+    fn test_cpp_parse_modules() {
+        let mut p = CppParser::new();
+        let units = [
+            ("a/util.h", ""),
+            ("b/util.h", "#include \"../a/util.h\""),
+            (
+                "b/main.cpp",
+                "#include \"util.h\"\n#include \"a/util.h\"\n#include <vector>",
+            ),
+        ];
+        for (unit, source_code) in units.iter() {
+            p.parse_module(unit, &source_code.as_bytes().to_vec());
+        }
+        p.finish();
+        let modules: Vec<(&str, Vec<&str>)> = p
+            .modules()
+            .iter()
+            .map(|x| {
+                let to = x.dependencies.iter().map(|x| x.to.as_str()).collect();
+                (x.name.as_str(), to)
+            })
+            .collect();
+        assert!(
+            modules
+                == vec![
+                    ("a/util.h", vec![]),
+                    ("b/util.h", vec!["a/util.h"]),
+                    ("b/main.cpp", vec!["b/util.h", "a/util.h"]),
+                ]
+        );
+    }
 }
@@ -1,7 +1,10 @@
 use crate::uml::*;
+use std::path::{Component, Path};
+use tree_sitter::Node;
 
 pub mod cpp_parser;
 pub mod rust_parser;
+pub mod schema;
 pub enum LangChoice {
     RUST,
     CPP,
@@ -12,4 +15,177 @@ pub trait LangParser {
     fn classes(&self) -> &Vec<UmlClass>;
     fn assocations(&self) -> &Vec<UmlAssociation>;
     fn enums(&self) -> &Vec<UmlEnum>;
+    fn blanket_impls(&self) -> &Vec<UmlBlanketImpl>;
+    /// Builds the control flow of the functions at `path`, relative to the parsed file. C++
+    /// overloads give a diagram each unless `path` names their parameters, `join(Game *)`.
+    fn parse_activity(&mut self, buffer: &Vec<u8>, path: &str) -> Vec<UmlActivityDiagram>;
+    /// Parses `buffer` as the Rust module or C++ translation unit `module`, recording its
+    /// dependencies on other modules next to the usual class model.
+    fn parse_module(&mut self, module: &str, buffer: &Vec<u8>);
+    fn modules(&self) -> &Vec<UmlModule>;
+    /// Resolves the references between the parsed files, called once after the last one.
+    fn finish(&mut self);
+}
+
+/// Adds `to` to `dependencies`, merging it into an existing dependency on the same module.
+pub fn add_module_dependency(dependencies: &mut Vec<UmlModuleDependency>, to: &str, weight: usize) {
+    match dependencies.iter_mut().find(|x| x.to == to) {
+        Some(dependency) => dependency.weight += weight,
+        None => dependencies.push(UmlModuleDependency {
+            to: to.to_owned(),
+            weight,
+        }),
+    }
+}
+
+/// Adds `module` to `modules`, merging it with a module of the same name parsed before.
+pub fn add_module(modules: &mut Vec<UmlModule>, module: UmlModule) {
+    match modules.iter_mut().find(|x| x.name == module.name) {
+        Some(existing) => {
+            for dependency in module.dependencies {
+                add_module_dependency(
+                    &mut existing.dependencies,
+                    &dependency.to,
+                    dependency.weight,
+                );
+            }
+        }
+        None => modules.push(module),
+    }
+}
+
+/// Adds the bases `name` inherits from, directly or not, to `out`.
+fn ancestors(classes: &Vec<UmlClass>, name: &str, out: &mut Vec<String>) {
+    if let Some(class) = classes.iter().find(|x| x.name == name) {
+        for base in class
+            .extends
+            .iter()
+            .map(|x| &x.name)
+            .chain(&class.implements)
+        {
+            if !out.contains(base) {
+                out.push(base.clone());
+                ancestors(classes, base, out);
+            }
+        }
+    }
+}
+
+/// Classes inheriting from a base through more than one of their direct bases, (`D`, `A`)
+/// for `D : B, C` with `B : A` and `C : A`. Only the nearest shared bases are reported.
+pub fn diamonds(classes: &Vec<UmlClass>) -> Vec<(String, String)> {
+    let mut v = vec![];
+    for class in classes {
+        let mut seen: Vec<String> = vec![];
+        let mut shared: Vec<String> = vec![];
+        for base in class
+            .extends
+            .iter()
+            .map(|x| &x.name)
+            .chain(&class.implements)
+        {
+            let mut reached = vec![base.clone()];
+            ancestors(classes, base, &mut reached);
+            for x in reached {
+                if !seen.contains(&x) {
+                    seen.push(x);
+                } else if !shared.contains(&x) {
+                    shared.push(x);
+                }
+            }
+        }
+        for x in &shared {
+            let above = shared.iter().any(|y| {
+                let mut bases = vec![];
+                ancestors(classes, y, &mut bases);
+                bases.contains(x)
+            });
+            if !above {
+                v.push((class.name.clone(), x.clone()));
+            }
+        }
+    }
+    v
+}
+
+/// Rust module path of a source file, `./src/sync/worker.rs` => `crate::sync::worker`.
+pub fn module_path(path: &Path) -> String {
+    let components: Vec<String> = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(x) => Some(x.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    let start = components
+        .iter()
+        .rposition(|x| x == "src")
+        .map_or(0, |x| x + 1);
+    let mut v = vec!["crate".to_owned()];
+    for (i, c) in components.iter().enumerate().skip(start) {
+        if i + 1 < components.len() {
+            v.push(c.to_owned());
+            continue;
+        }
+        match c.trim_end_matches(".rs") {
+            "mod" | "lib" | "main" => {}
+            stem => v.push(stem.to_owned()),
+        }
+    }
+    v.join("::")
+}
+
+/// The child of `node` in the field `name` the grammar guarantees to be present.
+pub fn field<'a>(node: Node<'a>, name: &str) -> Node<'a> {
+    node.child_by_field_name(name).unwrap()
+}
+
+/// All children of `node` in the field `name`, e.g. each declarator of `int a, b;`.
+pub fn fields<'a>(node: Node<'a>, name: &str) -> Vec<Node<'a>> {
+    let mut v = vec![];
+    let mut cursor = node.walk();
+    if cursor.goto_first_child() {
+        loop {
+            if cursor.field_name() == Some(name) {
+                v.push(cursor.node());
+            }
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+    v
+}
+
+/// Collapses all whitespace of `text` into single spaces.
+pub fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Collects `node` and its named descendants of one of the given kinds in document order.
+pub fn descendants_of_kind<'a>(node: Node<'a>, kinds: &[&str]) -> Vec<Node<'a>> {
+    let mut v = vec![];
+    // a cursor loses the alias of its root, `node` gives the kind `type_identifier`
+    if kinds.contains(&node.kind()) {
+        v.push(node);
+    }
+    let mut cursor = node.walk();
+    if !cursor.goto_first_child() {
+        return v;
+    }
+    loop {
+        let x = cursor.node();
+        if x.is_named() && kinds.contains(&x.kind()) {
+            v.push(x);
+        }
+        if cursor.goto_first_child() {
+            continue;
+        }
+        // the cursor cannot leave `node`, reaching it again ends the walk
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return v;
+            }
+        }
+    }
 }
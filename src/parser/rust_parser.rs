@@ -1,4 +1,7 @@
+use crate::parser::schema::*;
+use crate::parser::{add_module, add_module_dependency, descendants_of_kind, field, one_line};
 use crate::uml::*;
+use std::ops::Range;
 use tree_sitter::{Node, Query, QueryCursor};
 pub const METHOD_ARGS_QUERY: &str = "
 (parameter pattern: (identifier) @function.parameter.name
@@ -17,24 +20,100 @@ pub const CLASS_FIELDS_QUERY: &str = "
   name: (field_identifier) @class.field.name
   type: (_) @class.field.type)
 ";
+pub const TRAIT_OBJECT_QUERY: &str = "
+[(dynamic_type trait: (_) @trait_object.name)
+ (abstract_type trait: (_) @trait_object.name)]
+";
+pub const CONVERSION_QUERY: &str = "
+(impl_item
+  trait: (generic_type
+    type: (_) @conversion.trait
+    type_arguments: (type_arguments (_) @conversion.argument))
+  type: (_) @conversion.type)
+";
+pub const ERROR_TYPE_QUERY: &str = "
+[(function_item
+  return_type: (generic_type
+    type: (_) @result.type
+    type_arguments: (type_arguments (_) (_) @result.error)))
+(function_signature_item
+  return_type: (generic_type
+    type: (_) @result.type
+    type_arguments: (type_arguments (_) (_) @result.error)))]
+";
+pub const BLANKET_IMPL_QUERY: &str = "
+(impl_item
+  type_parameters: (type_parameters) @blanket.parameters
+  trait: (_) @blanket.trait
+  type: (_) @blanket.type) @blanket
+";
 pub const CLASS_QUERY: &str = "
-(
-  (struct_item
-    name: (type_identifier) @struct.name
-    body: (field_declaration_list ) @class.fields)
-  (impl_item
-    type: (type_identifier) @class.name
-    body: (declaration_list) @class.functions)+
-(#eq? @struct.name @class.name))";
+(struct_item
+  name: (type_identifier) @class.name
+  body: (field_declaration_list) @class.fields)
+";
+pub const ENUM_QUERY: &str = "
+(enum_item
+  name: (type_identifier) @enum.name
+  body: (enum_variant_list) @enum.variants)
+";
+pub const IMPL_QUERY: &str = "
+(impl_item
+  trait: (_)? @impl.trait
+  type: [(type_identifier) @impl.type
+         (generic_type type: (type_identifier) @impl.type)]
+  body: (declaration_list) @impl.functions)
+";
+
+/// Auto traits, `dyn Read + Send` is an interface `Read` and not one `Send`.
+const AUTO_TRAITS: [&str; 3] = ["Send", "Sync", "Unpin"];
+const PRELUDE_TYPES: [&str; 8] = [
+    "Self", "Box", "Option", "Result", "String", "Vec", "Rc", "Arc",
+];
+/// Derives mapping a struct to a database table.
+const TABLE_DERIVES: [&str; 8] = [
+    "Queryable",
+    "Insertable",
+    "Identifiable",
+    "AsChangeset",
+    "Selectable",
+    "QueryableByName",
+    "DeriveEntityModel",
+    "FromRow",
+];
 
 pub struct RustParser {
     ts_parser: tree_sitter::Parser,
     classes: Vec<UmlClass>,
     enums: Vec<UmlEnum>,
+    blanket_impls: Vec<UmlBlanketImpl>,
+    state_machines: Vec<UmlStateMachine>,
+    conversion_graph: UmlConversionGraph,
+    modules: Vec<UmlModule>,
+    module: String,
+    /// Paths used by each module, resolved against the parsed modules in `finish`.
+    module_paths: Vec<(String, String)>,
+    /// Row types of sqlx queries with the table they read, matched to the structs in `finish`.
+    row_tables: Vec<(String, String)>,
+    /// Parsed files and `mod` items, the only paths taken for modules.
+    known_modules: Vec<String>,
+    feature_gates: Vec<UmlFeatureGate>,
+    schema: UmlSchema,
+    macro_inventory: UmlMacroInventory,
+    ffi: UmlFfiBoundary,
+    scan_bodies: bool,
+    /// The diagram to build, only its passes run. All of them run when it is not set.
+    diagram: Option<String>,
     method_query: Query,
     method_args_query: Query,
     class_query: Query,
     class_fields_query: Query,
+    enum_query: Query,
+    impl_query: Query,
+    trait_object_query: Query,
+    conversion_query: Query,
+    error_type_query: Query,
+    blanket_impl_query: Query,
 }
 impl crate::parser::LangParser for RustParser {
     fn parse(&mut self, buffer: &Vec<u8>) {
@@ -42,7 +121,74 @@ impl crate::parser::LangParser for RustParser {
             .ts_parser
             .parse(buffer, None)
             .expect("Error Parsing root node!");
-        self.classes.append(&mut self.parse_classes(tree.root_node(), buffer));
+        if self.wants(&["class", "sequence"]) {
+            self.classes
+                .append(&mut self.parse_classes(tree.root_node(), buffer));
+        }
+        if self.wants(&["class", "state"]) {
+            let mut enums = self.parse_enums(tree.root_node(), buffer);
+            if self.wants(&["state"]) {
+                let mut state_machines =
+                    self.parse_state_machines(tree.root_node(), buffer, &enums);
+                self.state_machines.append(&mut state_machines);
+            }
+            self.enums.append(&mut enums);
+        }
+        if self.wants(&["class"]) {
+            self.blanket_impls
+                .append(&mut self.parse_blanket_impls(tree.root_node(), buffer));
+        }
+        if self.wants(&["conversions"]) {
+            self.conversion_graph
+                .conversions
+                .append(&mut self.parse_conversions(tree.root_node(), buffer));
+            for error_type in self.parse_error_types(tree.root_node(), buffer) {
+                if !self.conversion_graph.error_types.contains(&error_type) {
+                    self.conversion_graph.error_types.push(error_type);
+                }
+            }
+        }
+        if self.wants(&["modules"]) {
+            let module = UmlModule {
+                name: self.module.clone(),
+                dependencies: vec![],
+            };
+            add_module(&mut self.modules, module);
+            for path in self.parse_module_paths(tree.root_node(), buffer) {
+                self.module_paths.push((self.module.clone(), path));
+            }
+            for module in self.parse_module_items(tree.root_node(), buffer) {
+                if !self.known_modules.contains(&module) {
+                    self.known_modules.push(module);
+                }
+            }
+        }
+        if self.wants(&["features"]) {
+            self.feature_gates
+                .append(&mut self.parse_feature_gates(tree.root_node(), buffer));
+        }
+        if self.wants(&["er"]) {
+            let mut schema = self.parse_schema(tree.root_node(), buffer);
+            self.schema.entities.append(&mut schema.entities);
+            self.schema.joins.append(&mut schema.joins);
+            self.schema.mappings.append(&mut schema.mappings);
+            self.schema.query_groups.append(&mut schema.query_groups);
+            self.row_tables
+                .append(&mut self.parse_row_queries(tree.root_node(), buffer));
+        }
+        if self.wants(&["macros"]) {
+            let mut inventory = self.parse_macros(tree.root_node(), buffer);
+            self.macro_inventory.macros.append(&mut inventory.macros);
+            self.macro_inventory
+                .invocations
+                .append(&mut inventory.invocations);
+        }
+        if self.wants(&["ffi"]) {
+            let mut ffi = self.parse_ffi(tree.root_node(), buffer);
+            self.ffi.imports.append(&mut ffi.imports);
+            self.ffi.exports.append(&mut ffi.exports);
+            self.ffi.types.append(&mut ffi.types);
+        }
     }
 
     fn classes(&self) -> &Vec<UmlClass> {
@@ -56,6 +202,126 @@ impl crate::parser::LangParser for RustParser {
     fn assocations(&self) -> &Vec<UmlAssociation> {
         todo!()
     }
+
+    fn blanket_impls(&self) -> &Vec<UmlBlanketImpl> {
+        &self.blanket_impls
+    }
+
+    fn parse_module(&mut self, module: &str, buffer: &Vec<u8>) {
+        self.module = module.to_owned();
+        if !self.known_modules.contains(&self.module) {
+            self.known_modules.push(self.module.clone());
+        }
+        self.parse(buffer);
+    }
+
+    fn finish(&mut self) {
+        // sqlx names the table in the queries reading a row, not on the struct of the row
+        let mappings = &mut self.schema.mappings;
+        let mut queried: Vec<usize> = vec![];
+        for (row, table) in self.row_tables.drain(..) {
+            let same = mappings
+                .iter()
+                .position(|x| x.type_name == row && x.table == table);
+            let guessed = mappings
+                .iter()
+                .position(|x| x.type_name == row && x.derives.iter().any(|x| x == "FromRow"));
+            match (same, guessed) {
+                (Some(i), _) => queried.push(i),
+                (None, Some(i)) if !queried.contains(&i) => {
+                    mappings[i].table = table;
+                    queried.push(i);
+                }
+                _ => mappings.push(UmlTableMapping {
+                    type_name: row,
+                    table,
+                    derives: vec!["query_as".to_owned()],
+                }),
+            }
+        }
+        // each item of another module counts once, `use db::Pool` and `Pool::new` are one
+        let mut items: Vec<(String, String)> = vec![];
+        for (module, path) in self.module_paths.drain(..) {
+            // the innermost parsed module the path goes through
+            let to = self
+                .known_modules
+                .iter()
+                .filter(|x| path == **x || path.starts_with(&format!("{}::", x)))
+                .max_by_key(|x| x.len());
+            let to = match to {
+                Some(to) if *to != module => to,
+                _ => continue,
+            };
+            let item = match path[to.len()..].trim_start_matches("::").split("::").next() {
+                Some(name) if !name.is_empty() => format!("{}::{}", to, name),
+                _ => to.clone(),
+            };
+            if items.contains(&(module.clone(), item.clone())) {
+                continue;
+            }
+            items.push((module.clone(), item));
+            let existing = self.modules.iter_mut().find(|x| x.name == module);
+            add_module_dependency(&mut existing.unwrap().dependencies, to, 1);
+        }
+        // inline modules and those of files not parsed are known by their `mod` item only
+        for name in &self.known_modules {
+            let module = UmlModule {
+                name: name.clone(),
+                dependencies: vec![],
+            };
+            add_module(&mut self.modules, module);
+        }
+    }
+
+    fn modules(&self) -> &Vec<UmlModule> {
+        &self.modules
+    }
+
+    fn parse_activity(&mut self, buffer: &Vec<u8>, path: &str) -> Vec<UmlActivityDiagram> {
+        let tree = match self.ts_parser.parse(buffer, None) {
+            Some(tree) => tree,
+            None => return vec![],
+        };
+        let segments: Vec<&str> = path.split("::").collect();
+        let name = segments[segments.len() - 1];
+        // the impl type or inline module owning the function
+        let owner = match segments.len() {
+            1 => None,
+            len => Some(segments[len - 2].to_owned()),
+        };
+        for function in descendants_of_kind(tree.root_node(), &["function_item"]) {
+            if field(function, "name").utf8_text(buffer).unwrap() != name {
+                continue;
+            }
+            let mut enclosing: Option<String> = None;
+            let mut parent = function.parent();
+            while let Some(p) = parent {
+                match p.kind() {
+                    "impl_item" => {
+                        let data_type = field(p, "type");
+                        enclosing = Some(type_name(data_type.utf8_text(buffer).unwrap()));
+                        break;
+                    }
+                    "mod_item" | "trait_item" => {
+                        let module = field(p, "name");
+                        enclosing = Some(module.utf8_text(buffer).unwrap().to_owned());
+                        break;
+                    }
+                    _ => parent = p.parent(),
+                }
+            }
+            match function.child_by_field_name("body") {
+                Some(body) if enclosing == owner => {
+                    return vec![UmlActivityDiagram {
+                        name: path.to_owned(),
+                        activities: self.parse_activities(body, buffer),
+                    }]
+                }
+                _ => {}
+            }
+        }
+        vec![]
+    }
 }
 impl RustParser {
     pub fn new() -> Self {
@@ -71,10 +337,77 @@ impl RustParser {
             method_query: Query::new(language, METHOD_QUERY).unwrap(),
             class_query: Query::new(language, CLASS_QUERY).unwrap(),
             class_fields_query: Query::new(language, CLASS_FIELDS_QUERY).unwrap(),
+            enum_query: Query::new(language, ENUM_QUERY).unwrap(),
+            impl_query: Query::new(language, IMPL_QUERY).unwrap(),
+            trait_object_query: Query::new(language, TRAIT_OBJECT_QUERY).unwrap(),
+            conversion_query: Query::new(language, CONVERSION_QUERY).unwrap(),
+            error_type_query: Query::new(language, ERROR_TYPE_QUERY).unwrap(),
+            blanket_impl_query: Query::new(language, BLANKET_IMPL_QUERY).unwrap(),
             classes: vec![],
             enums: vec![],
+            blanket_impls: vec![],
+            state_machines: vec![],
+            modules: vec![],
+            module: "crate".to_owned(),
+            module_paths: vec![],
+            row_tables: vec![],
+            known_modules: vec![],
+            feature_gates: vec![],
+            schema: UmlSchema {
+                entities: vec![],
+                joins: vec![],
+                mappings: vec![],
+                query_groups: vec![],
+            },
+            macro_inventory: UmlMacroInventory {
+                macros: vec![],
+                invocations: vec![],
+            },
+            ffi: UmlFfiBoundary {
+                imports: vec![],
+                exports: vec![],
+                types: vec![],
+            },
+            scan_bodies: false,
+            diagram: None,
+            conversion_graph: UmlConversionGraph {
+                conversions: vec![],
+                error_types: vec![],
+            },
+        }
+    }
+    /// Enables the pass recording the types used in method bodies as `uses` of their class.
+    pub fn set_scan_bodies(&mut self, scan_bodies: bool) {
+        self.scan_bodies = scan_bodies;
+    }
+    /// Restricts the passes run on each file to those `diagram` is drawn from.
+    pub fn set_diagram(&mut self, diagram: &str) {
+        self.diagram = Some(diagram.to_owned());
+    }
+    fn wants(&self, diagrams: &[&str]) -> bool {
+        match &self.diagram {
+            Some(diagram) => diagrams.contains(&diagram.as_str()),
+            None => true,
         }
     }
+    pub fn conversion_graph(&self) -> &UmlConversionGraph {
+        &self.conversion_graph
+    }
+    pub fn state_machines(&self) -> &Vec<UmlStateMachine> {
+        &self.state_machines
+    }
+    pub fn feature_gates(&self) -> &Vec<UmlFeatureGate> {
+        &self.feature_gates
+    }
+    pub fn schema(&self) -> &UmlSchema {
+        &self.schema
+    }
+    pub fn macro_inventory(&self) -> &UmlMacroInventory {
+        &self.macro_inventory
+    }
+    pub fn ffi(&self) -> &UmlFfiBoundary {
+        &self.ffi
+    }
     pub fn parse_method_args(&self, node: Node, buffer: &Vec<u8>) -> Vec<UmlParameter> {
         let mut v = vec![];
 
@@ -139,9 +472,13 @@ impl RustParser {
                 }
                 v.push(UmlMethod {
                     name: name.as_ref().unwrap().to_string(),
+                    kind: UmlMethodKind::Method,
                     visibility,
                     parameters,
                     return_type,
+                    calls: vec![],
+                    template_parameters: vec![],
+                    modifiers: vec![],
                 });
             });
         v
@@ -177,144 +514,1399 @@ impl RustParser {
                     name: name.as_ref().unwrap().to_string(),
                     data_type: data_type.as_ref().unwrap().to_string(),
                     visibility: visibility,
+                    modifiers: vec![],
                 });
             });
         v
     }
-    pub fn parse_classes(&self, node: Node, buffer: &Vec<u8>) -> Vec<UmlClass> {
-        // struct + impl => class
-        // classes
-        let mut classes: Vec<UmlClass> = vec![];
+    /// Collects the traits behind `dyn Trait` and `impl Trait` types below `node`.
+    fn parse_trait_objects(&self, node: Node, buffer: &[u8]) -> Vec<String> {
+        let mut v: Vec<String> = vec![];
+        QueryCursor::new()
+            .matches(&self.trait_object_query, node, |x| {
+                x.utf8_text(buffer).unwrap()
+            })
+            .for_each(|m| {
+                for c in m.captures.iter() {
+                    // `impl Into<String> + Clone` adds its further bounds around the first
+                    let mut bounds = vec![c.node];
+                    let mut parent = c.node.parent().and_then(|x| x.parent());
+                    while let Some(p) = parent.filter(|x| x.kind() == "bounded_type") {
+                        for i in 1..p.named_child_count() {
+                            bounds.push(p.named_child(i).unwrap());
+                        }
+                        parent = p.parent();
+                    }
+                    for bound in bounds {
+                        // closures like `dyn Fn(u8)` are no interfaces
+                        if bound.kind() == "function_type" || bound.kind() == "lifetime" {
+                            continue;
+                        }
+                        let name = type_name(bound.utf8_text(buffer).unwrap());
+                        if !AUTO_TRAITS.contains(&name.as_str()) && !v.contains(&name) {
+                            v.push(name);
+                        }
+                    }
+                }
+            });
+        v
+    }
+    /// Fields typed by a trait object are associations to the trait itself.
+    fn parse_polymorphic_associations(&self, node: Node, buffer: &[u8]) -> Vec<UmlAssociation> {
+        let mut v = vec![];
         QueryCursor::new()
-            .matches(&self.class_query, node, |x| x.utf8_text(buffer).unwrap())
+            .matches(&self.class_fields_query, node, |x| {
+                x.utf8_text(buffer).unwrap()
+            })
             .for_each(|m| {
                 let mut name: Option<String> = None;
-                let mut fields: Vec<UmlField> = vec![];
-                let mut methods: Vec<UmlMethod> = vec![];
-                let mut associations: Vec<UmlAssociation> = vec![];
-                let mut extends: Vec<String> = vec![];
-                let mut implements: Vec<String> = vec![];
+                let mut traits: Vec<String> = vec![];
+                for c in m.captures.iter() {
+                    match self.class_fields_query.capture_names()[c.index as usize].as_str() {
+                        "class.field.name" => {
+                            name = Some(c.node.utf8_text(buffer).unwrap().to_owned())
+                        }
+                        "class.field.type" => traits = self.parse_trait_objects(c.node, buffer),
+                        _ => {}
+                    }
+                }
+                for to in traits {
+                    v.push(UmlAssociation {
+                        to,
+                        from_title: name.as_ref().unwrap().to_string(),
+                        to_title: name.as_ref().unwrap().to_string(),
+                        polymorphic: true,
+                        is_static: false,
+                    });
+                }
+            });
+        v
+    }
+    /// Parameters and return types typed by a trait object are dependencies on the trait.
+    fn parse_polymorphic_dependencies(&self, node: Node, buffer: &[u8]) -> Vec<UmlDependency> {
+        let mut v: Vec<UmlDependency> = vec![];
+        QueryCursor::new()
+            .matches(&self.method_query, node, |x| x.utf8_text(buffer).unwrap())
+            .for_each(|m| {
+                for c in m.captures.iter() {
+                    match self.method_query.capture_names()[c.index as usize].as_str() {
+                        "function.parameters" | "function.return_type" => {
+                            for to in self.parse_trait_objects(c.node, buffer) {
+                                if !v.iter().any(|d| d.to == to) {
+                                    v.push(UmlDependency {
+                                        to,
+                                        polymorphic: true,
+                                    });
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            });
+        v
+    }
+    /// `From`, `TryFrom`, `Into` and `AsRef` impls as edges from the source to the target type.
+    pub fn parse_conversions(&self, node: Node, buffer: &Vec<u8>) -> Vec<UmlConversion> {
+        let mut v = vec![];
+        let aliases = self.use_aliases(node, buffer);
+        QueryCursor::new()
+            .matches(&self.conversion_query, node, |x| {
+                x.utf8_text(buffer).unwrap()
+            })
+            .for_each(|m| {
+                let mut kind: Option<String> = None;
+                let mut argument: Option<String> = None;
+                let mut data_type: Option<String> = None;
+                for c in m.captures.iter() {
+                    let text = c.node.utf8_text(buffer).unwrap();
+                    match self.conversion_query.capture_names()[c.index as usize].as_str() {
+                        "conversion.trait" => kind = Some(type_name(text)),
+                        "conversion.argument" => {
+                            argument = Some(self.qualified_type(c.node, buffer, &aliases))
+                        }
+                        "conversion.type" => {
+                            data_type = Some(self.qualified_type(c.node, buffer, &aliases))
+                        }
+                        _ => {}
+                    }
+                }
+                let (kind, argument, data_type) =
+                    (kind.unwrap(), argument.unwrap(), data_type.unwrap());
+                match kind.as_str() {
+                    "From" | "TryFrom" => v.push(UmlConversion {
+                        from: argument,
+                        to: data_type,
+                        kind,
+                    }),
+                    "Into" | "AsRef" => v.push(UmlConversion {
+                        from: data_type,
+                        to: argument,
+                        kind,
+                    }),
+                    _ => {}
+                }
+            });
+        v
+    }
+    /// Types used as `E` in `Result<T, E>` return types.
+    pub fn parse_error_types(&self, node: Node, buffer: &Vec<u8>) -> Vec<String> {
+        let mut v: Vec<String> = vec![];
+        let aliases = self.use_aliases(node, buffer);
+        QueryCursor::new()
+            .matches(&self.error_type_query, node, |x| {
+                x.utf8_text(buffer).unwrap()
+            })
+            .for_each(|m| {
+                let mut is_result = false;
+                let mut error: Option<String> = None;
+                for c in m.captures.iter() {
+                    let text = c.node.utf8_text(buffer).unwrap();
+                    match self.error_type_query.capture_names()[c.index as usize].as_str() {
+                        "result.type" => is_result = type_name(text) == "Result",
+                        "result.error" => {
+                            error = Some(self.qualified_type(c.node, buffer, &aliases))
+                        }
+                        _ => {}
+                    }
+                }
+                match error {
+                    Some(error) if is_result && !v.contains(&error) => v.push(error),
+                    _ => {}
+                }
+            });
+        v
+    }
+    /// Generic impls like `impl<T: Display> MyTrait for T` or `impl<T> MyTrait for &T`
+    /// which have no single target class.
+    pub fn parse_blanket_impls(&self, node: Node, buffer: &[u8]) -> Vec<UmlBlanketImpl> {
+        let mut v = vec![];
+        QueryCursor::new()
+            .matches(&self.blanket_impl_query, node, |x| {
+                x.utf8_text(buffer).unwrap()
+            })
+            .for_each(|m| {
+                let mut parameters: Vec<String> = vec![];
+                let mut bounds: Vec<String> = vec![];
+                let mut trait_name: Option<String> = None;
+                let mut target: Option<Node> = None;
                 for c in m.captures.iter() {
-                    match self.class_query.capture_names()[c.index as usize].as_str() {
-                        "class.fields" => {
-                            fields = self.parse_class_fields(c.node, buffer);
+                    match self.blanket_impl_query.capture_names()[c.index as usize].as_str() {
+                        "blanket.parameters" => {
+                            for i in 0..c.node.named_child_count() {
+                                let parameter = c.node.named_child(i).unwrap();
+                                match parameter.kind() {
+                                    "type_identifier" => parameters
+                                        .push(parameter.utf8_text(buffer).unwrap().to_owned()),
+                                    "constrained_type_parameter" => {
+                                        let left = parameter.child_by_field_name("left").unwrap();
+                                        if left.kind() != "lifetime" {
+                                            parameters
+                                                .push(left.utf8_text(buffer).unwrap().to_owned());
+                                            bounds.push(
+                                                parameter.utf8_text(buffer).unwrap().to_owned(),
+                                            );
+                                        }
+                                    }
+                                    _ => {}
+                                }
+                            }
                         }
-                        "class.name" => name = Some(c.node.utf8_text(buffer).unwrap().to_owned()),
-                        "class.functions" => {
-                            methods = self.parse_methods(c.node, buffer);
+                        "blanket.trait" => {
+                            trait_name = Some(type_name(c.node.utf8_text(buffer).unwrap()))
+                        }
+                        "blanket.type" => target = Some(c.node),
+                        "blanket" => {
+                            // the where clause of the impl itself, not the ones of its methods
+                            for i in 0..c.node.named_child_count() {
+                                let clause = c.node.named_child(i).unwrap();
+                                if clause.kind() != "where_clause" {
+                                    continue;
+                                }
+                                for j in 0..clause.named_child_count() {
+                                    let predicate = clause.named_child(j).unwrap();
+                                    if predicate.kind() == "where_predicate" {
+                                        bounds
+                                            .push(predicate.utf8_text(buffer).unwrap().to_owned());
+                                    }
+                                }
+                            }
                         }
                         _ => {}
                     }
                 }
-                classes.push(UmlClass {
-                    name: name.expect("No class name found"),
-                    fields,
-                    methods,
-                    modifier: None,
-                    visibility: UmlVisibility::Public,
-                    extends,
-                    implements,
-                    associations,
-                })
+                let target = target.unwrap();
+                // `impl<T> Foo for Stack<T>` is a regular impl of the generic type `Stack`
+                let parameter = match target.kind() {
+                    "reference_type" => field(target, "type"),
+                    _ => target,
+                };
+                let is_parameter = parameter.kind() == "type_identifier"
+                    && parameters.contains(&parameter.utf8_text(buffer).unwrap().to_owned());
+                if is_parameter {
+                    v.push(UmlBlanketImpl {
+                        trait_name: trait_name.unwrap(),
+                        target: target.utf8_text(buffer).unwrap().to_owned(),
+                        bounds,
+                    });
+                }
+            });
+        v
+    }
+    /// Impl blocks keyed by the name of the implementing type.
+    fn parse_impls(&self, node: Node, buffer: &[u8]) -> Vec<ImplBlock> {
+        let mut v = vec![];
+        for m in
+            QueryCursor::new().matches(&self.impl_query, node, |x| x.utf8_text(buffer).unwrap())
+        {
+            let mut name: Option<String> = None;
+            let mut trait_name: Option<String> = None;
+            let mut body: Option<Range<usize>> = None;
+            for c in m.captures.iter() {
+                match self.impl_query.capture_names()[c.index as usize].as_str() {
+                    "impl.type" => name = Some(c.node.utf8_text(buffer).unwrap().to_owned()),
+                    "impl.trait" => trait_name = Some(type_name(c.node.utf8_text(buffer).unwrap())),
+                    "impl.functions" => body = Some(c.node.byte_range()),
+                    _ => {}
+                }
+            }
+            v.push(ImplBlock {
+                name: name.unwrap(),
+                trait_name,
+                body: body.unwrap(),
             });
+        }
+        v
+    }
+    pub fn parse_classes(&self, node: Node, buffer: &Vec<u8>) -> Vec<UmlClass> {
+        // struct + impl => class
+        // classes
+        let mut classes: Vec<UmlClass> = vec![];
+        let impls = self.parse_impls(node, buffer);
+        for m in
+            QueryCursor::new().matches(&self.class_query, node, |x| x.utf8_text(buffer).unwrap())
+        {
+            let mut name: Option<String> = None;
+            let mut fields: Vec<UmlField> = vec![];
+            let mut methods: Vec<UmlMethod> = vec![];
+            let mut associations: Vec<UmlAssociation> = vec![];
+            let mut dependencies: Vec<UmlDependency> = vec![];
+            let extends: Vec<UmlGeneralization> = vec![];
+            let mut implements: Vec<String> = vec![];
+            let mut uses: Vec<String> = vec![];
+            for c in m.captures.iter() {
+                match self.class_query.capture_names()[c.index as usize].as_str() {
+                    "class.fields" => {
+                        fields = self.parse_class_fields(c.node, buffer);
+                        associations = self.parse_polymorphic_associations(c.node, buffer);
+                    }
+                    "class.name" => name = Some(c.node.utf8_text(buffer).unwrap().to_owned()),
+                    _ => {}
+                }
+            }
+            let name = name.expect("No class name found");
+            let own_impls: Vec<&ImplBlock> = impls.iter().filter(|x| x.name == name).collect();
+            if own_impls.is_empty() {
+                continue;
+            }
+            for i in own_impls {
+                let mut impl_methods = self.parse_methods(i.body(node), buffer);
+                // the query yields the methods in the same document order
+                let functions = descendants_of_kind(i.body(node), &["function_item"]);
+                for (method, function) in impl_methods.iter_mut().zip(functions) {
+                    method.calls =
+                        self.parse_calls(function, buffer, &name, &fields, &method.parameters);
+                }
+                methods.append(&mut impl_methods);
+                for d in self.parse_polymorphic_dependencies(i.body(node), buffer) {
+                    if !dependencies.iter().any(|x| x.to == d.to) {
+                        dependencies.push(d);
+                    }
+                }
+                match &i.trait_name {
+                    Some(t) if !implements.contains(t) => implements.push(t.to_owned()),
+                    _ => {}
+                }
+                if self.scan_bodies {
+                    for used in self.parse_body_uses(i.body(node), buffer) {
+                        if used != name && !uses.contains(&used) {
+                            uses.push(used);
+                        }
+                    }
+                }
+            }
+            classes.push(UmlClass {
+                name,
+                fields,
+                methods,
+                modifier: None,
+                visibility: UmlVisibility::Public,
+                extends,
+                implements,
+                associations,
+                dependencies,
+                uses,
+                owner: None,
+                package: None,
+                template_parameters: vec![],
+                specializes: None,
+            })
+        }
 
         classes
     }
-}
-#[cfg(test)]
-mod tests {
-    // Note this useful idiom: importing names from outer (for mod tests) scope.
-    use super::*;
-    use crate::parser::*;
-    use crate::uml::*;
-
-    #[test]
-    fn test_rust_parse_methods() {
-        let mut p = RustParser::new();
-        let source_code = "
-impl X {
-pub fn func1() -> i32 {}
-fn func2() -> i32 {}
-}
-";
-        let tree = p.ts_parser.parse(source_code, None).unwrap();
-        let root_node = tree.root_node();
-
-        let methods = p.parse_methods(root_node, &source_code.as_bytes().to_vec());
-        let m0 = methods.get(0).unwrap();
-        assert!(m0.name == "func1");
-        assert!(m0.return_type.as_ref().unwrap() == "i32");
-        assert!(matches!(m0.visibility, UmlVisibility::Public));
-
-        let m1 = methods.get(1).unwrap();
-        assert!(matches!(m1.visibility, UmlVisibility::Private));
+    /// Types named by paths like `Foo::new()`, `Bar::helper()` or `Baz::Variant` and by
+    /// struct expressions in the function bodies below `node`.
+    fn parse_body_uses(&self, node: Node, buffer: &[u8]) -> Vec<String> {
+        let mut v: Vec<String> = vec![];
+        for function in descendants_of_kind(node, &["function_item"]) {
+            let body = match function.child_by_field_name("body") {
+                Some(body) => body,
+                None => continue,
+            };
+            let kinds = [
+                "scoped_identifier",
+                "scoped_type_identifier",
+                "struct_expression",
+            ];
+            for path in descendants_of_kind(body, &kinds) {
+                let used = match path.kind() {
+                    "struct_expression" => path.child_by_field_name("name"),
+                    _ => path.child_by_field_name("path"),
+                };
+                let used = match used {
+                    Some(used) => type_name(used.utf8_text(buffer).unwrap()),
+                    None => continue,
+                };
+                // module paths are lowercase, well known std types would only add noise
+                if used.starts_with(char::is_uppercase)
+                    && !PRELUDE_TYPES.contains(&used.as_str())
+                    && !v.contains(&used)
+                {
+                    v.push(used);
+                }
+            }
+        }
+        v
     }
-
-    #[test]
-    fn test_rust_parse_method_args() {
-        let mut p = RustParser::new();
-        let source_code = "
-impl X {
-pub fn func1(arg1: i32, arg2: i32) -> i32 {}
-}
-";
-        let tree = p.ts_parser.parse(source_code, None).unwrap();
-        let root_node = tree.root_node();
-
-        let args = p.parse_method_args(root_node, &source_code.as_bytes().to_vec());
-        assert!(args.get(0).unwrap().name == "arg1");
-        assert!(args.get(0).unwrap().data_type == "i32");
-        assert!(args.get(1).unwrap().name == "arg2");
-        assert!(args.get(1).unwrap().data_type == "i32");
-
-        assert!(args.get(2).is_none());
+    /// The absolute paths of the crate used in the file, through use declarations and
+    /// qualified paths. Paths starting with an imported name go through its import.
+    fn parse_module_paths(&self, node: Node, buffer: &Vec<u8>) -> Vec<String> {
+        let aliases = self.use_aliases(node, buffer);
+        let mut v: Vec<String> = aliases
+            .iter()
+            .filter(|(_, path)| path.starts_with("crate"))
+            .map(|(_, path)| path.clone())
+            .collect();
+        for path in descendants_of_kind(node, &["scoped_identifier", "scoped_type_identifier"]) {
+            // only the outermost node of a path, use declarations are handled above
+            let parent = path.parent().unwrap();
+            if parent.kind().starts_with("scoped_") || parent.kind().starts_with("use_") {
+                continue;
+            }
+            let path = path.utf8_text(buffer).unwrap();
+            if let Some(path) = absolute_path(&self.module, path, &aliases) {
+                v.push(path);
+            }
+        }
+        v
     }
-
-    #[test]
-    fn test_rust_parse_classes_2_impl() {
-        let mut p = RustParser::new();
-        let source_code = "
-struct X {
-}
-impl X {
-pub fn func1() -> i32 {}
-}
-impl A for X {
-pub fn func2() -> i32 {}
-}
-";
-        let tree = p.ts_parser.parse(source_code, None).unwrap();
-        let root_node = tree.root_node();
-
-        let classes = p.parse_classes(root_node, &source_code.as_bytes().to_vec());
-        assert!(classes.get(0).unwrap().name == "X");
-        assert!(classes.get(1).is_none());
+    /// The names brought in scope by the use declarations with the paths they stand for,
+    /// paths of the crate start at `crate`, those of other crates are kept as written.
+    fn use_aliases(&self, node: Node, buffer: &Vec<u8>) -> Vec<(String, String)> {
+        let mut v = vec![];
+        for declaration in descendants_of_kind(node, &["use_declaration"]) {
+            for (path, name) in use_paths(field(declaration, "argument"), buffer, "") {
+                let path = absolute_path(&self.module, &path, &[]).unwrap_or(path);
+                v.push((name, path));
+            }
+        }
+        v
     }
-    #[test]
-    fn test_rust_parse_classes() {
-        let mut p = RustParser::new();
-        let source_code = "
-struct X {
-pub a: i32,
-b: i32,
-}
-impl X {
-pub fn func1() -> i32 {}
-}
-
-struct Y {
-a: i32,
-b: i32,
-}
-impl Y {
-pub fn func1() -> i32 {}
-}
-";
-        let tree = p.ts_parser.parse(source_code, None).unwrap();
-        let root_node = tree.root_node();
-
-        let classes = p.parse_classes(root_node, &source_code.as_bytes().to_vec());
-
+    /// The type `node` by its path, `io::Error` => `std::io::Error` after `use std::io`.
+    /// Unqualified types not imported are taken to be declared in the module of `node`,
+    /// the type parameters of the items around it, `T` of `impl<T> From<T>`, are kept.
+    fn qualified_type(&self, node: Node, buffer: &[u8], aliases: &[(String, String)]) -> String {
+        let (path, arguments) = match node.kind() {
+            "generic_type" => (
+                field(node, "type"),
+                field(node, "type_arguments").utf8_text(buffer).unwrap(),
+            ),
+            "type_identifier" | "scoped_type_identifier" => (node, ""),
+            _ => return node.utf8_text(buffer).unwrap().to_owned(),
+        };
+        let path = path.utf8_text(buffer).unwrap();
+        if type_parameters_around(node, buffer)
+            .iter()
+            .any(|x| x == path)
+        {
+            return node.utf8_text(buffer).unwrap().to_owned();
+        }
+        let module = self.node_module(node, buffer);
+        let path = match absolute_path(&module, path, aliases) {
+            Some(path) => path,
+            None if path.contains("::") || PRELUDE_TYPES.contains(&path) => path.to_owned(),
+            None => format!("{}::{}", module, path),
+        };
+        format!("{}{}", path, arguments)
+    }
+    /// The modules declared with `mod` items, inline or in their own file.
+    fn parse_module_items(&self, node: Node, buffer: &[u8]) -> Vec<String> {
+        descendants_of_kind(node, &["mod_item"])
+            .iter()
+            .map(|x| {
+                let name = field(*x, "name").utf8_text(buffer).unwrap();
+                format!("{}::{}", self.node_module(*x, buffer), name)
+            })
+            .collect()
+    }
+    /// Items below `node` gated by `#[cfg(feature = "...")]`, named by their module path.
+    /// An inner `#![cfg(...)]` gates the whole module.
+    fn parse_feature_gates(&self, node: Node, buffer: &Vec<u8>) -> Vec<UmlFeatureGate> {
+        let mut v = vec![];
+        for attribute in descendants_of_kind(node, &["attribute_item", "inner_attribute_item"]) {
+            let cfg = match attribute.named_child(0) {
+                Some(x) if x.kind() == "meta_item" && meta_name(x, buffer) == "cfg" => x,
+                _ => continue,
+            };
+            let item = match attribute.kind() {
+                "inner_attribute_item" => self.module.clone(),
+                _ => {
+                    let mut item = attribute.next_named_sibling();
+                    while let Some(x) = item {
+                        match x.kind() {
+                            "attribute_item" | "line_comment" | "block_comment" => {
+                                item = x.next_named_sibling()
+                            }
+                            _ => break,
+                        }
+                    }
+                    match item.and_then(|x| self.item_path(x, buffer)) {
+                        Some(item) => item,
+                        None => continue,
+                    }
+                }
+            };
+            let mut features = vec![];
+            cfg_features(cfg, buffer, &mut features);
+            for feature in features {
+                v.push(UmlFeatureGate {
+                    feature,
+                    item: item.clone(),
+                });
+            }
+        }
+        v
+    }
+    /// Tables and joins of Diesel's `table!` and `joinable!` macros, SeaORM entity models
+    /// and the structs deriving a mapping to a table.
+    fn parse_schema(&self, node: Node, buffer: &[u8]) -> UmlSchema {
+        let mut schema = UmlSchema {
+            entities: vec![],
+            joins: vec![],
+            mappings: vec![],
+            query_groups: vec![],
+        };
+        for invocation in descendants_of_kind(node, &["macro_invocation"]) {
+            let name = type_name(field(invocation, "macro").utf8_text(buffer).unwrap());
+            let body = invocation.utf8_text(buffer).unwrap();
+            // everything after `table!`
+            let body = body.split_once('!').map_or("", |x| x.1);
+            match name.as_str() {
+                "table" => schema.entities.append(&mut parse_table_macro(body)),
+                "joinable" => schema.joins.extend(parse_joinable_macro(body)),
+                "allow_tables_to_appear_in_same_query" => {
+                    schema.query_groups.push(parse_query_group_macro(body))
+                }
+                _ => {}
+            }
+        }
+        for item in descendants_of_kind(node, &["struct_item"]) {
+            let item_attributes = attributes(item, buffer);
+            let derives: Vec<String> = derives(&item_attributes)
+                .into_iter()
+                .filter(|x| TABLE_DERIVES.contains(&x.as_str()))
+                .collect();
+            if derives.is_empty() {
+                continue;
+            }
+            let name = field(item, "name").utf8_text(buffer).unwrap().to_owned();
+            let table = item_attributes
+                .iter()
+                .find_map(|x| table_name_attribute(x))
+                .unwrap_or_else(|| default_table_name(&name));
+            // SeaORM declares the columns by the fields of the model
+            if derives.iter().any(|x| x == "DeriveEntityModel") {
+                let mut columns = vec![];
+                for declaration in descendants_of_kind(item, &["field_declaration"]) {
+                    columns.push(UmlColumn {
+                        name: field(declaration, "name")
+                            .utf8_text(buffer)
+                            .unwrap()
+                            .to_owned(),
+                        data_type: field(declaration, "type")
+                            .utf8_text(buffer)
+                            .unwrap()
+                            .to_owned(),
+                        primary_key: attributes(declaration, buffer)
+                            .iter()
+                            .any(|x| x.contains("primary_key")),
+                    });
+                }
+                schema.entities.push(UmlEntity {
+                    name: table.clone(),
+                    columns,
+                });
+            }
+            schema.mappings.push(UmlTableMapping {
+                type_name: name,
+                table,
+                derives,
+            });
+        }
+        schema
+    }
+    /// The row types and tables of sqlx's `query_as!(User, "...")` and
+    /// `query_as::<_, User>("...")`.
+    fn parse_row_queries(&self, node: Node, buffer: &[u8]) -> Vec<(String, String)> {
+        let mut v = vec![];
+        for query in descendants_of_kind(node, &["macro_invocation", "call_expression"]) {
+            if query.kind() == "macro_invocation" {
+                let name = type_name(field(query, "macro").utf8_text(buffer).unwrap());
+                if name == "query_as" || name == "query_as_unchecked" {
+                    let body = query.utf8_text(buffer).unwrap();
+                    v.extend(parse_query_as_macro(
+                        body.split_once('!').map_or("", |x| x.1),
+                    ));
+                }
+                continue;
+            }
+            let function = field(query, "function");
+            if function.kind() != "generic_function"
+                || type_name(field(function, "function").utf8_text(buffer).unwrap()) != "query_as"
+            {
+                continue;
+            }
+            let arguments = field(function, "type_arguments");
+            let row = arguments
+                .named_child(arguments.named_child_count() - 1)
+                .unwrap();
+            let literal = descendants_of_kind(
+                field(query, "arguments"),
+                &["string_literal", "raw_string_literal"],
+            );
+            if let Some(table) = literal
+                .first()
+                .and_then(|x| query_table(x.utf8_text(buffer).unwrap()))
+            {
+                v.push((type_name(row.utf8_text(buffer).unwrap()), table));
+            }
+        }
+        v
+    }
+    /// `macro_rules!` definitions and the macro invocations standing in for items below `node`.
+    fn parse_macros(&self, node: Node, buffer: &[u8]) -> UmlMacroInventory {
+        let mut inventory = UmlMacroInventory {
+            macros: vec![],
+            invocations: vec![],
+        };
+        for definition in descendants_of_kind(node, &["macro_definition"]) {
+            let rules = descendants_of_kind(definition, &["macro_rule"])
+                .iter()
+                .filter_map(|x| x.child_by_field_name("left"))
+                .map(|x| one_line(x.utf8_text(buffer).unwrap()))
+                .collect();
+            inventory.macros.push(UmlMacro {
+                name: field(definition, "name")
+                    .utf8_text(buffer)
+                    .unwrap()
+                    .to_owned(),
+                module: self.node_module(definition, buffer),
+                rules,
+            });
+        }
+        for invocation in descendants_of_kind(node, &["macro_invocation"]) {
+            // `foo!(...);` is wrapped into a statement
+            let mut parent = invocation.parent().unwrap();
+            if parent.kind() == "expression_statement" {
+                parent = parent.parent().unwrap();
+            }
+            let owner = match parent.kind() {
+                "source_file" => None,
+                "declaration_list" => match parent.parent() {
+                    Some(x) if x.kind() == "impl_item" => {
+                        Some(type_name(field(x, "type").utf8_text(buffer).unwrap()))
+                    }
+                    _ => None,
+                },
+                _ => continue,
+            };
+            inventory.invocations.push(UmlMacroInvocation {
+                name: field(invocation, "macro")
+                    .utf8_text(buffer)
+                    .unwrap()
+                    .to_owned(),
+                module: self.node_module(invocation, buffer),
+                owner,
+            });
+        }
+        inventory
+    }
+    /// Functions of `extern` blocks, `#[no_mangle]` functions and `#[repr(C)]` types below `node`.
+    fn parse_ffi(&self, node: Node, buffer: &Vec<u8>) -> UmlFfiBoundary {
+        let mut ffi = UmlFfiBoundary {
+            imports: vec![],
+            exports: vec![],
+            types: vec![],
+        };
+        // a plain `extern` defaults to the C ABI
+        let abi = |x: Node| match descendants_of_kind(x, &["extern_modifier"]).first() {
+            Some(modifier) if modifier.utf8_text(buffer).unwrap() == "extern" => {
+                "extern \"C\"".to_owned()
+            }
+            Some(modifier) => one_line(modifier.utf8_text(buffer).unwrap()),
+            None => "Rust".to_owned(),
+        };
+        for block in descendants_of_kind(node, &["foreign_mod_item"]) {
+            let body = match block.child_by_field_name("body") {
+                Some(body) => body,
+                None => continue,
+            };
+            let abi = abi(block);
+            for function in descendants_of_kind(body, &["function_signature_item"]) {
+                ffi.imports
+                    .push(self.parse_ffi_function(function, buffer, abi.clone()));
+            }
+        }
+        for function in descendants_of_kind(node, &["function_item"]) {
+            let exported = attributes(function, buffer)
+                .iter()
+                .any(|x| x.contains("no_mangle") || x.contains("export_name"));
+            if !exported {
+                continue;
+            }
+            let modifiers = (0..function.named_child_count())
+                .filter_map(|i| function.named_child(i))
+                .find(|x| x.kind() == "function_modifiers");
+            let abi = match modifiers {
+                Some(x) => abi(x),
+                None => "Rust".to_owned(),
+            };
+            ffi.exports
+                .push(self.parse_ffi_function(function, buffer, abi));
+        }
+        for item in descendants_of_kind(node, &["struct_item", "enum_item", "union_item"]) {
+            // `#[repr(C)]` and `#[repr(C, packed)]`
+            let repr_c = attributes(item, buffer)
+                .iter()
+                .any(|x| match x.find("repr(") {
+                    Some(i) => x[i + "repr(".len()..]
+                        .split(')')
+                        .next()
+                        .unwrap()
+                        .split(',')
+                        .any(|x| x.trim() == "C"),
+                    None => false,
+                });
+            if repr_c {
+                ffi.types
+                    .push(field(item, "name").utf8_text(buffer).unwrap().to_owned());
+            }
+        }
+        ffi
+    }
+    fn parse_ffi_function(&self, function: Node, buffer: &Vec<u8>, abi: String) -> UmlFfiFunction {
+        UmlFfiFunction {
+            name: field(function, "name")
+                .utf8_text(buffer)
+                .unwrap()
+                .to_owned(),
+            abi,
+            parameters: self.parse_method_args(field(function, "parameters"), buffer),
+            return_type: function
+                .child_by_field_name("return_type")
+                .map(|x| x.utf8_text(buffer).unwrap().to_owned()),
+        }
+    }
+    /// Module `node` is declared in, including the inline `mod` blocks around it.
+    fn node_module(&self, node: Node, buffer: &[u8]) -> String {
+        let mut modules = vec![];
+        let mut parent = node.parent();
+        while let Some(p) = parent {
+            if p.kind() == "mod_item" {
+                modules.insert(0, field(p, "name").utf8_text(buffer).unwrap());
+            }
+            parent = p.parent();
+        }
+        modules.insert(0, &self.module);
+        modules.join("::")
+    }
+    /// Path of an item like `crate::db::Storage::open`, including the type it is declared in.
+    fn item_path(&self, item: Node, buffer: &[u8]) -> Option<String> {
+        let mut name = match (item.kind(), item.child_by_field_name("name")) {
+            ("impl_item", _) => format!("impl {}", field(item, "type").utf8_text(buffer).unwrap()),
+            (_, Some(name)) => name.utf8_text(buffer).unwrap().to_owned(),
+            _ => return None,
+        };
+        let mut parent = item.parent();
+        while let Some(p) = parent {
+            let owner = match p.kind() {
+                "impl_item" => p.child_by_field_name("type"),
+                "struct_item" | "enum_item" | "trait_item" | "mod_item" => {
+                    p.child_by_field_name("name")
+                }
+                _ => None,
+            };
+            if let Some(owner) = owner {
+                name = format!("{}::{}", type_name(owner.utf8_text(buffer).unwrap()), name);
+            }
+            parent = p.parent();
+        }
+        Some(format!("{}::{}", self.module, name))
+    }
+    /// Calls in the body of `function`, resolved through `self`, fields and parameters.
+    fn parse_calls(
+        &self,
+        function: Node,
+        buffer: &[u8],
+        class: &str,
+        fields: &[UmlField],
+        parameters: &[UmlParameter],
+    ) -> Vec<UmlCall> {
+        let mut v = vec![];
+        let body = match function.child_by_field_name("body") {
+            Some(body) => body,
+            None => return v,
+        };
+        let text = |x: Node| x.utf8_text(buffer).unwrap().to_owned();
+        // in the order of evaluation, a call ends after the calls in its receiver and arguments
+        let mut calls = descendants_of_kind(body, &["call_expression"]);
+        calls.sort_by_key(|x| x.end_byte());
+        for call in calls {
+            let callee = field(call, "function");
+            let (receiver, method) = match callee.kind() {
+                "field_expression" => {
+                    let value = field(callee, "value");
+                    let receiver = match value.kind() {
+                        "self" => Some(class.to_owned()),
+                        "field_expression" if field(value, "value").kind() == "self" => {
+                            let name = text(field(value, "field"));
+                            fields
+                                .iter()
+                                .find(|x| x.name == name)
+                                .map(|x| receiver_type(&x.data_type))
+                        }
+                        "identifier" => {
+                            let name = text(value);
+                            parameters
+                                .iter()
+                                .find(|x| x.name == name)
+                                .map(|x| receiver_type(&x.data_type))
+                        }
+                        _ => None,
+                    };
+                    (receiver, text(field(callee, "field")))
+                }
+                "scoped_identifier" => {
+                    let receiver = match callee.child_by_field_name("path") {
+                        Some(path) if text(path) == "Self" => Some(class.to_owned()),
+                        Some(path) => Some(type_name(&text(path))),
+                        None => None,
+                    };
+                    (receiver, text(field(callee, "name")))
+                }
+                _ => (None, text(callee)),
+            };
+            v.push(UmlCall {
+                receiver,
+                method,
+                text: one_line(&text(call)),
+            });
+        }
+        v
+    }
+    pub fn parse_enums(&self, node: Node, buffer: &Vec<u8>) -> Vec<UmlEnum> {
+        let mut enums: Vec<UmlEnum> = vec![];
+        let impls = self.parse_impls(node, buffer);
+        for m in
+            QueryCursor::new().matches(&self.enum_query, node, |x| x.utf8_text(buffer).unwrap())
+        {
+            let mut name: Option<String> = None;
+            let mut variants: Vec<String> = vec![];
+            let mut methods: Vec<UmlMethod> = vec![];
+            let mut implements: Vec<String> = vec![];
+            let mut uses: Vec<String> = vec![];
+            for c in m.captures.iter() {
+                match self.enum_query.capture_names()[c.index as usize].as_str() {
+                    "enum.name" => name = Some(c.node.utf8_text(buffer).unwrap().to_owned()),
+                    "enum.variants" => {
+                        for variant in descendants_of_kind(c.node, &["enum_variant"]) {
+                            let variant_name = variant.child_by_field_name("name").unwrap();
+                            variants.push(variant_name.utf8_text(buffer).unwrap().to_owned());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            let name = name.expect("No enum name found");
+            for i in impls.iter().filter(|x| x.name == name) {
+                methods.append(&mut self.parse_methods(i.body(node), buffer));
+                match &i.trait_name {
+                    Some(t) if !implements.contains(t) => implements.push(t.to_owned()),
+                    _ => {}
+                }
+                if self.scan_bodies {
+                    for used in self.parse_body_uses(i.body(node), buffer) {
+                        if used != name && !uses.contains(&used) {
+                            uses.push(used);
+                        }
+                    }
+                }
+            }
+            enums.push(UmlEnum {
+                name,
+                values: variants.iter().map(|_| None).collect(),
+                variants,
+                methods,
+                implements,
+                uses,
+                underlying_type: None,
+                is_scoped: false,
+                owner: None,
+                package: None,
+            })
+        }
+        enums
+    }
+    /// Control flow of the statements in `block`.
+    fn parse_activities(&self, block: Node, buffer: &Vec<u8>) -> Vec<UmlActivity> {
+        let mut v = vec![];
+        for i in 0..block.named_child_count() {
+            v.append(&mut self.parse_activity_statement(block.named_child(i).unwrap(), buffer));
+        }
+        v
+    }
+    fn parse_activity_statement(&self, node: Node, buffer: &Vec<u8>) -> Vec<UmlActivity> {
+        let text = |x: Node| one_line(x.utf8_text(buffer).unwrap());
+        match node.kind() {
+            "line_comment" | "block_comment" => vec![],
+            "expression_statement" => {
+                self.parse_activity_statement(node.named_child(0).unwrap(), buffer)
+            }
+            "block" => self.parse_activities(node, buffer),
+            "if_expression" | "if_let_expression" => {
+                let condition = match node.kind() {
+                    "if_expression" => text(field(node, "condition")),
+                    _ => format!(
+                        "let {} = {}",
+                        text(field(node, "pattern")),
+                        text(field(node, "value"))
+                    ),
+                };
+                let otherwise = match node.child_by_field_name("alternative") {
+                    Some(alternative) => {
+                        self.parse_activity_statement(alternative.named_child(0).unwrap(), buffer)
+                    }
+                    None => vec![],
+                };
+                vec![UmlActivity::Decision {
+                    condition,
+                    then: self.parse_activities(field(node, "consequence"), buffer),
+                    otherwise,
+                }]
+            }
+            "match_expression" => {
+                let body = field(node, "body");
+                let mut cases = vec![];
+                for arm in descendants_of_kind(body, &["match_arm"]) {
+                    if arm.parent() != Some(body) {
+                        continue;
+                    }
+                    cases.push(UmlActivityCase {
+                        pattern: text(field(arm, "pattern")),
+                        activities: self.parse_activity_statement(field(arm, "value"), buffer),
+                    });
+                }
+                vec![UmlActivity::Switch {
+                    subject: text(field(node, "value")),
+                    cases,
+                }]
+            }
+            // a bare `loop` repeats until it breaks out
+            "loop_expression" => vec![UmlActivity::Repeat {
+                condition: "true".to_owned(),
+                body: self.parse_activities(field(node, "body"), buffer),
+            }],
+            "while_expression" | "while_let_expression" | "for_expression" => {
+                let condition = match node.kind() {
+                    "while_expression" => text(field(node, "condition")),
+                    "while_let_expression" => format!(
+                        "let {} = {}",
+                        text(field(node, "pattern")),
+                        text(field(node, "value"))
+                    ),
+                    _ => format!(
+                        "for {} in {}",
+                        text(field(node, "pattern")),
+                        text(field(node, "value"))
+                    ),
+                };
+                vec![UmlActivity::Loop {
+                    condition,
+                    body: self.parse_activities(field(node, "body"), buffer),
+                }]
+            }
+            "return_expression" => vec![UmlActivity::Return(text(node))],
+            "let_declaration" => match node.child_by_field_name("value") {
+                Some(value)
+                    if [
+                        "if_expression",
+                        "if_let_expression",
+                        "match_expression",
+                        "loop_expression",
+                    ]
+                    .contains(&value.kind()) =>
+                {
+                    self.parse_activity_statement(value, buffer)
+                }
+                _ => self.parse_activity_action(node, buffer),
+            },
+            _ => self.parse_activity_action(node, buffer),
+        }
+    }
+    fn parse_activity_action(&self, node: Node, buffer: &[u8]) -> Vec<UmlActivity> {
+        let text = one_line(node.utf8_text(buffer).unwrap());
+        let text = text.trim_end_matches(';').to_owned();
+        if !descendants_of_kind(node, &["try_expression"]).is_empty() {
+            vec![UmlActivity::Try(text)]
+        } else if !descendants_of_kind(node, &["return_expression"]).is_empty() {
+            vec![UmlActivity::Return(text)]
+        } else {
+            vec![UmlActivity::Action(text)]
+        }
+    }
+    /// Scans the `match` arms in the impl methods of `enums` for arms whose value names
+    /// another variant of the matched enum.
+    pub fn parse_state_machines(
+        &self,
+        node: Node,
+        buffer: &Vec<u8>,
+        enums: &Vec<UmlEnum>,
+    ) -> Vec<UmlStateMachine> {
+        let mut machines: Vec<UmlStateMachine> = vec![];
+        let impls = self.parse_impls(node, buffer);
+        for e in enums {
+            let mut transitions: Vec<UmlTransition> = vec![];
+            for i in impls.iter().filter(|x| x.name == e.name) {
+                for function in descendants_of_kind(i.body(node), &["function_item"]) {
+                    let method = function.child_by_field_name("name").unwrap();
+                    let method = method.utf8_text(buffer).unwrap();
+                    for arm in descendants_of_kind(function, &["match_arm"]) {
+                        let pattern = arm.child_by_field_name("pattern").unwrap();
+                        let value = arm.child_by_field_name("value").unwrap();
+                        let label = format!(
+                            "{} / {}",
+                            method,
+                            one_line(pattern.utf8_text(buffer).unwrap())
+                        );
+                        let mut targets = vec![];
+                        state_targets(value, buffer, e, true, &mut targets);
+                        for from in variants_used(pattern, buffer, e) {
+                            for to in targets.iter() {
+                                transitions.push(UmlTransition {
+                                    from: from.to_owned(),
+                                    to: to.to_owned(),
+                                    label: label.to_owned(),
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            if !transitions.is_empty() {
+                machines.push(UmlStateMachine {
+                    name: e.name.to_owned(),
+                    states: e.variants.iter().map(|x| x.to_owned()).collect(),
+                    transitions,
+                });
+            }
+        }
+        machines
+    }
+}
+
+/// The type parameters declared by `node` and the items containing it, `T` and `U` of
+/// `impl<T: Clone, U = u8>`.
+fn type_parameters_around(node: Node, buffer: &[u8]) -> Vec<String> {
+    let mut v = vec![];
+    let mut parent = Some(node);
+    while let Some(p) = parent {
+        if let Some(parameters) = p.child_by_field_name("type_parameters") {
+            for i in 0..parameters.named_child_count() {
+                let parameter = parameters.named_child(i).unwrap();
+                let name = match parameter.kind() {
+                    "type_identifier" => Some(parameter),
+                    "constrained_type_parameter" => parameter.child_by_field_name("left"),
+                    "optional_type_parameter" => parameter.child_by_field_name("name"),
+                    _ => None,
+                };
+                if let Some(name) = name.filter(|x| x.kind() == "type_identifier") {
+                    v.push(name.utf8_text(buffer).unwrap().to_owned());
+                }
+            }
+        }
+        parent = p.parent();
+    }
+    v
+}
+/// The variants of `e` a match arm value goes to: the value of its tail expression, of a
+/// `return` or of an assignment. A nested match in tail position goes on to the values of
+/// its arms, other nested matches and closures are left to themselves.
+fn state_targets(node: Node, buffer: &Vec<u8>, e: &UmlEnum, tail: bool, out: &mut Vec<String>) {
+    let mut target = |x: Option<Node>| {
+        if let Some(variant) = x.and_then(|x| variant_of(x, buffer, e)) {
+            if !out.contains(&variant) {
+                out.push(variant);
+            }
+        }
+    };
+    match node.kind() {
+        // `State::Idle => match event { .. }` goes wherever its arms go
+        "match_expression" if tail => {
+            let arms = field(node, "body");
+            for i in 0..arms.named_child_count() {
+                if let Some(value) = arms.named_child(i).unwrap().child_by_field_name("value") {
+                    state_targets(value, buffer, e, true, out);
+                }
+            }
+            return;
+        }
+        "match_expression" | "closure_expression" | "function_item" => return,
+        "return_expression" => return target(node.named_child(0)),
+        "assignment_expression" => return target(node.child_by_field_name("right")),
+        _ if tail => target(Some(node)),
+        _ => {}
+    }
+    let last = node.named_child_count().checked_sub(1);
+    for i in 0..node.named_child_count() {
+        let child = node.named_child(i).unwrap();
+        // the value of a block is its last expression, the ones of an if are its branches
+        let tail = tail
+            && match node.kind() {
+                "block" => Some(i) == last && !child.kind().ends_with("statement"),
+                "if_expression" | "if_let_expression" => {
+                    Some(child) == node.child_by_field_name("consequence")
+                        || Some(child) == node.child_by_field_name("alternative")
+                }
+                "else_clause" => true,
+                _ => false,
+            };
+        state_targets(child, buffer, e, tail, out);
+    }
+}
+/// The variant of `e` built by `node`, `Light::Red`, `Self::Blinking(3)` or `State::On { .. }`.
+fn variant_of(node: Node, buffer: &[u8], e: &UmlEnum) -> Option<String> {
+    let path = match node.kind() {
+        "scoped_identifier" => node,
+        "call_expression" => node.child_by_field_name("function")?,
+        "struct_expression" => node.child_by_field_name("name")?,
+        _ => return None,
+    };
+    let prefix = type_name(path.child_by_field_name("path")?.utf8_text(buffer).unwrap());
+    let name = path.child_by_field_name("name")?.utf8_text(buffer).unwrap();
+    match (prefix == e.name || prefix == "Self") && e.variants.iter().any(|x| x == name) {
+        true => Some(name.to_owned()),
+        false => None,
+    }
+}
+/// Variants of `e` referenced as `Enum::Variant` or `Self::Variant` below `node`.
+fn variants_used(node: Node, buffer: &[u8], e: &UmlEnum) -> Vec<String> {
+    let mut v: Vec<String> = vec![];
+    for path in descendants_of_kind(node, &["scoped_identifier", "scoped_type_identifier"]) {
+        let (prefix, name) = match (
+            path.child_by_field_name("path"),
+            path.child_by_field_name("name"),
+        ) {
+            (Some(prefix), Some(name)) => (prefix, name),
+            _ => continue,
+        };
+        let prefix = type_name(prefix.utf8_text(buffer).unwrap());
+        let name = name.utf8_text(buffer).unwrap().to_owned();
+        if (prefix == e.name || prefix == "Self")
+            && e.variants.contains(&name)
+            && !v.contains(&name)
+        {
+            v.push(name);
+        }
+    }
+    v
+}
+
+/// The names in `feature = "a"` of a cfg predicate, also inside of `all(...)` and `any(...)`.
+/// A negated `not(...)` predicate does not gate the item on its features.
+fn cfg_features(meta: Node, buffer: &Vec<u8>, out: &mut Vec<String>) {
+    match meta_name(meta, buffer) {
+        "not" => return,
+        "feature" => {
+            if let Some(value) = meta.child_by_field_name("value") {
+                let value = value.utf8_text(buffer).unwrap();
+                out.push(value.trim_matches('"').to_owned());
+            }
+            return;
+        }
+        _ => {}
+    }
+    if let Some(arguments) = meta.child_by_field_name("arguments") {
+        for i in 0..arguments.named_child_count() {
+            let argument = arguments.named_child(i).unwrap();
+            if argument.kind() == "meta_item" {
+                cfg_features(argument, buffer, out);
+            }
+        }
+    }
+}
+/// The path of a meta item, `cfg` of `cfg(test)`.
+fn meta_name<'a>(meta: Node, buffer: &'a [u8]) -> &'a str {
+    match meta.named_child(0) {
+        Some(x) => x.utf8_text(buffer).unwrap(),
+        None => "",
+    }
+}
+/// Texts of the outer attributes of `item`, e.g. `#[derive(Debug)]`.
+fn attributes(item: Node, buffer: &[u8]) -> Vec<String> {
+    let mut v = vec![];
+    let mut sibling = item.prev_named_sibling();
+    while let Some(x) = sibling {
+        match x.kind() {
+            "attribute_item" => v.insert(0, x.utf8_text(buffer).unwrap().to_owned()),
+            "line_comment" | "block_comment" => {}
+            _ => break,
+        }
+        sibling = x.prev_named_sibling();
+    }
+    v
+}
+
+/// Traits named in `#[derive(...)]` attributes, `#[derive(diesel::Queryable)]` => `Queryable`.
+fn derives(attributes: &Vec<String>) -> Vec<String> {
+    let mut v = vec![];
+    for attribute in attributes {
+        let list = match attribute.find("derive(") {
+            Some(i) => &attribute[i + "derive(".len()..],
+            None => continue,
+        };
+        let list = list.split(')').next().unwrap_or(list);
+        v.extend(list.split(',').map(type_name).filter(|x| !x.is_empty()));
+    }
+    v
+}
+
+/// The paths of a use declaration with the name each one binds, `a::{b, c as d}` =>
+/// `(a::b, b)` and `(a::c, d)`.
+fn use_paths(node: Node, buffer: &Vec<u8>, prefix: &str) -> Vec<(String, String)> {
+    let join = |path: Node| match prefix {
+        "" => path.utf8_text(buffer).unwrap().to_owned(),
+        _ => format!("{}::{}", prefix, path.utf8_text(buffer).unwrap()),
+    };
+    match node.kind() {
+        "use_as_clause" => {
+            let alias = field(node, "alias").utf8_text(buffer).unwrap();
+            let path = use_paths(field(node, "path"), buffer, prefix);
+            path.into_iter()
+                .map(|(x, _)| (x, alias.to_owned()))
+                .collect()
+        }
+        "use_list" => (0..node.named_child_count())
+            .flat_map(|i| use_paths(node.named_child(i).unwrap(), buffer, prefix))
+            .collect(),
+        "scoped_use_list" => {
+            let prefix = match node.child_by_field_name("path") {
+                Some(path) => join(path),
+                None => prefix.to_owned(),
+            };
+            use_paths(field(node, "list"), buffer, &prefix)
+        }
+        // `self` in a list binds the module of the list
+        "self" if !prefix.is_empty() => {
+            let name = prefix.rsplit("::").next().unwrap();
+            vec![(prefix.to_owned(), name.to_owned())]
+        }
+        _ => {
+            let path = join(node);
+            let name = path.rsplit("::").next().unwrap().to_owned();
+            vec![(path, name)]
+        }
+    }
+}
+
+/// The path from the crate root of `path` used in the module `current`, going through the
+/// imported `aliases` by their name. External crates and names of the prelude give `None`.
+fn absolute_path(current: &str, path: &str, aliases: &[(String, String)]) -> Option<String> {
+    let mut segments = path.split("::").map(|x| x.trim());
+    let mut absolute: Vec<&str> = match segments.next()? {
+        "crate" => vec!["crate"],
+        "self" => current.split("::").collect(),
+        "super" => {
+            let mut module: Vec<&str> = current.split("::").collect();
+            module.pop();
+            module
+        }
+        first => match aliases.iter().find(|(name, _)| name == first) {
+            Some((_, path)) => path.split("::").collect(),
+            None => return None,
+        },
+    };
+    for segment in segments {
+        match segment {
+            "super" => {
+                absolute.pop();
+            }
+            "self" | "*" => {}
+            s => absolute.push(s),
+        }
+    }
+    match absolute.is_empty() {
+        true => None,
+        false => Some(absolute.join("::")),
+    }
+}
+
+/// The type method calls on a value of `data_type` dispatch to, `&mut Box<dyn Storage>` => `Storage`.
+fn receiver_type(data_type: &str) -> String {
+    let mut t = data_type.trim();
+    loop {
+        t = t
+            .trim_start_matches('&')
+            .trim_start()
+            .trim_start_matches("mut ")
+            .trim_start_matches("dyn ")
+            .trim_start_matches("impl ")
+            .trim();
+        match t.find('<') {
+            Some(i) if t.ends_with('>') => match type_name(&t[..i]).as_str() {
+                "Box" | "Rc" | "Arc" | "RefCell" | "Cell" | "Mutex" | "RwLock" | "Option" => {
+                    t = &t[i + 1..t.len() - 1];
+                }
+                _ => return type_name(t),
+            },
+            _ => return type_name(t),
+        }
+    }
+}
+
+/// The query matches borrow their cursor, so the body is kept as a byte range of the tree.
+struct ImplBlock {
+    name: String,
+    trait_name: Option<String>,
+    body: Range<usize>,
+}
+
+impl ImplBlock {
+    fn body<'a>(&self, root: Node<'a>) -> Node<'a> {
+        root.descendant_for_byte_range(self.body.start, self.body.end)
+            .unwrap()
+    }
+}
+
+/// Strips the module path and generic arguments of a type, `crate::db::Storage<T>` => `Storage`.
+fn type_name(data_type: &str) -> String {
+    let base = data_type.split('<').next().unwrap_or(data_type);
+    base.rsplit("::").next().unwrap_or(base).trim().to_owned()
+}
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::parser::*;
+    use crate::uml::*;
+
+    #[test]
+    fn test_rust_parse_methods() {
+        let mut p = RustParser::new();
+        let source_code = "
+impl X {
+pub fn func1() -> i32 {}
+fn func2() -> i32 {}
+}
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+
+        let methods = p.parse_methods(root_node, &source_code.as_bytes().to_vec());
+        let m0 = methods.get(0).unwrap();
+        assert!(m0.name == "func1");
+        assert!(m0.return_type.as_ref().unwrap() == "i32");
+        assert!(matches!(m0.visibility, UmlVisibility::Public));
+
+        let m1 = methods.get(1).unwrap();
+        assert!(matches!(m1.visibility, UmlVisibility::Private));
+    }
+
+    #[test]
+    fn test_rust_parse_method_args() {
+        let mut p = RustParser::new();
+        let source_code = "
+impl X {
+pub fn func1(arg1: i32, arg2: i32) -> i32 {}
+}
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+
+        let args = p.parse_method_args(root_node, &source_code.as_bytes().to_vec());
+        assert!(args.get(0).unwrap().name == "arg1");
+        assert!(args.get(0).unwrap().data_type == "i32");
+        assert!(args.get(1).unwrap().name == "arg2");
+        assert!(args.get(1).unwrap().data_type == "i32");
+
+        assert!(args.get(2).is_none());
+    }
+
+    #[test]
+    fn test_rust_parse_classes_2_impl() {
+        let mut p = RustParser::new();
+        let source_code = "
+struct X {
+}
+impl X {
+pub fn func1() -> i32 {}
+}
+impl A for X {
+pub fn func2() -> i32 {}
+}
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+
+        let classes = p.parse_classes(root_node, &source_code.as_bytes().to_vec());
+        assert!(classes.get(0).unwrap().name == "X");
+        assert!(classes.get(1).is_none());
+    }
+    #[test]
+    fn test_rust_parse_classes() {
+        let mut p = RustParser::new();
+        let source_code = "
+struct X {
+pub a: i32,
+b: i32,
+}
+impl X {
+pub fn func1() -> i32 {}
+}
+
+struct Y {
+a: i32,
+b: i32,
+}
+impl Y {
+pub fn func1() -> i32 {}
+}
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+
+        let classes = p.parse_classes(root_node, &source_code.as_bytes().to_vec());
+
         assert!(classes.get(0).unwrap().name == "X");
         assert!(classes.get(0).unwrap().fields.get(0).unwrap().name == "a");
         assert!(classes.get(0).unwrap().fields.get(0).unwrap().data_type == "i32");
@@ -343,4 +1935,795 @@ pub fn func1() -> i32 {}
 
         assert!(classes.get(2).is_none());
     }
+
+    #[test]
+    fn test_rust_parse_trait_object_dependencies() {
+        let mut p = RustParser::new();
+        let source_code = "
+struct X {
+storage: Box<dyn Storage>,
+callback: Box<dyn Fn(u8)>,
+sink: Box<dyn Write + Send + 'static>,
+marker: Arc<dyn Send + Sync>,
+}
+impl X {
+pub fn set(&mut self, s: &dyn Storage) {}
+pub fn with(c: impl crate::io::Codec<u8>) -> impl Iterator<Item = u8> {}
+pub fn rename(name: impl Into<String> + Clone) {}
+}
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+
+        let classes = p.parse_classes(root_node, &source_code.as_bytes().to_vec());
+        let x = &classes[0];
+
+        // every bound is a trait, but the auto traits
+        let associations: Vec<(&str, &str)> = x
+            .associations
+            .iter()
+            .map(|x| (x.to_title.as_str(), x.to.as_str()))
+            .collect();
+        assert!(associations == vec![("storage", "Storage"), ("sink", "Write")]);
+        assert!(x.associations.iter().all(|x| x.polymorphic));
+
+        let dependencies: Vec<&str> = x.dependencies.iter().map(|x| x.to.as_str()).collect();
+        assert!(dependencies == vec!["Storage", "Codec", "Iterator", "Into", "Clone"]);
+        assert!(x.dependencies.iter().all(|d| d.polymorphic));
+    }
+
+    #[test]
+    fn test_rust_parse_conversions() {
+        let mut p = RustParser::new();
+        let source_code = "
+use std::io;
+use crate::error::AppError;
+impl From<io::Error> for AppError {}
+impl std::convert::TryFrom<u8> for State {}
+impl Into<String> for Name {}
+impl AsRef<[u8]> for Name {}
+impl Display for Name {}
+fn load() -> Result<Config, AppError> {}
+fn store() -> io::Result<()> {}
+fn save() -> Result<(), crate::error::AppError> {}
+fn parse() -> Result<u8, ParseError> {}
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+        let buffer = source_code.as_bytes().to_vec();
+
+        let conversions = p.parse_conversions(root_node, &buffer);
+        assert!(conversions.get(0).unwrap().from == "std::io::Error");
+        assert!(conversions.get(0).unwrap().to == "crate::error::AppError");
+        assert!(conversions.get(0).unwrap().kind == "From");
+        assert!(conversions.get(1).unwrap().from == "u8");
+        assert!(conversions.get(1).unwrap().kind == "TryFrom");
+        assert!(conversions.get(2).unwrap().from == "crate::Name");
+        assert!(conversions.get(2).unwrap().to == "String");
+        assert!(conversions.get(3).unwrap().to == "[u8]");
+        assert!(conversions.get(4).is_none());
+
+        let error_types = p.parse_error_types(root_node, &buffer);
+        assert!(error_types == vec!["crate::error::AppError", "crate::ParseError"]);
+    }
+
+    #[test]
+    fn test_rust_parse_generic_conversions() {
+        use crate::generator::plantuml::PlantUml;
+        let mut p = RustParser::new();
+        let source_code = "
+impl<T> From<T> for Wrapper<T> {}
+impl<E: Error> From<E> for Report {}
+impl From<Token> for Wrapper<Token> {}
+";
+        let buffer = source_code.as_bytes().to_vec();
+        p.parse(&buffer);
+
+        let conversions: Vec<(&str, &str)> = p
+            .conversion_graph()
+            .conversions
+            .iter()
+            .map(|x| (x.from.as_str(), x.to.as_str()))
+            .collect();
+        // the parameters of the impl are no types of the crate
+        assert!(
+            conversions
+                == vec![
+                    ("T", "crate::Wrapper<T>"),
+                    ("E", "crate::Report"),
+                    ("crate::Token", "crate::Wrapper<Token>"),
+                ]
+        );
+        let plantuml = p.conversion_graph().to_plantuml();
+        assert!(plantuml.contains("\"T\" --> \"crate::Wrapper<T>\""));
+    }
+
+    #[test]
+    fn test_rust_parse_blanket_impls() {
+        let mut p = RustParser::new();
+        let source_code = "
+impl<T: Display> MyTrait for T {}
+impl<'a, T> MyTrait for &'a T where T: Clone {
+fn map<U>(&self, u: U) where U: Copy {}
+}
+impl<T> MyTrait for Vec<T> {}
+impl<T> MyTrait for Wrapper<u8> {}
+impl MyTrait for X {}
+struct Stack<T> {
+items: Vec<T>,
+}
+impl<T: Display> Display for Stack<T> {}
+impl<T> Stack<T> {
+fn push(&mut self, item: T) {}
+}
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+        let buffer = source_code.as_bytes().to_vec();
+
+        let impls = p.parse_blanket_impls(root_node, &buffer);
+        assert!(impls.get(0).unwrap().trait_name == "MyTrait");
+        assert!(impls.get(0).unwrap().target == "T");
+        assert!(impls.get(0).unwrap().bounds == vec!["T: Display".to_owned()]);
+        assert!(impls.get(1).unwrap().target == "&'a T");
+        assert!(impls.get(1).unwrap().bounds == vec!["T: Clone".to_owned()]);
+        assert!(impls.get(2).is_none());
+
+        // impls of a generic type belong to its class
+        let classes = p.parse_classes(root_node, &buffer);
+        assert!(classes.get(0).unwrap().name == "Stack");
+        assert!(classes.get(0).unwrap().implements == vec!["Display".to_owned()]);
+        assert!(classes.get(0).unwrap().methods.get(0).unwrap().name == "push");
+    }
+
+    #[test]
+    fn test_rust_parse_classes_unrelated_impl() {
+        let mut p = RustParser::new();
+        let source_code = "
+struct X {
+}
+impl X {
+pub fn func1() -> i32 {}
+}
+impl Display for X {
+fn fmt(&self, f: &mut Formatter) -> Result {}
+}
+impl From<io::Error> for AppError {
+fn from(e: io::Error) -> Self {}
+}
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+
+        let classes = p.parse_classes(root_node, &source_code.as_bytes().to_vec());
+        assert!(classes.get(0).unwrap().name == "X");
+        assert!(classes.get(0).unwrap().methods.get(0).unwrap().name == "func1");
+        assert!(classes.get(0).unwrap().methods.get(1).unwrap().name == "fmt");
+        assert!(classes.get(0).unwrap().methods.get(2).is_none());
+        assert!(classes.get(0).unwrap().implements == vec!["Display".to_owned()]);
+        assert!(classes.get(1).is_none());
+    }
+
+    #[test]
+    fn test_rust_parse_enums() {
+        let mut p = RustParser::new();
+        let source_code = "
+enum State {
+Idle,
+Running(u8),
+Done { code: i32 },
+}
+impl State {
+pub fn next(&self) -> State {}
+}
+impl fmt::Display for State {
+fn fmt(&self, f: &mut Formatter) -> Result {}
+}
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+
+        let enums = p.parse_enums(root_node, &source_code.as_bytes().to_vec());
+        let state = enums.get(0).unwrap();
+        assert!(state.name == "State");
+        assert!(state.variants == vec!["Idle", "Running", "Done"]);
+        assert!(state.methods.get(0).unwrap().name == "next");
+        assert!(matches!(
+            state.methods.get(0).unwrap().visibility,
+            UmlVisibility::Public
+        ));
+        assert!(state.methods.get(1).unwrap().name == "fmt");
+        assert!(state.implements == vec!["Display".to_owned()]);
+        assert!(enums.get(1).is_none());
+    }
+
+    #[test]
+    fn test_rust_parse_state_machines() {
+        let mut p = RustParser::new();
+        let source_code = "
+enum Light {
+Red,
+Green,
+Blinking(u8),
+}
+impl Light {
+pub fn next(&self) -> Light {
+match self {
+Light::Red => Light::Green,
+Self::Green => if true { Light::Red } else { Light::Blinking(3) },
+Light::Blinking(_) => Light::Blinking(0),
+}
+}
+pub fn reset(&mut self) {
+match self {
+Light::Blinking(_) => *self = Light::Red,
+Light::Green => {
+if self.is(Light::Red) {
+return;
+}
+match self.is(Light::Red) {
+true => Light::Red,
+false => Light::Green,
+};
+}
+_ => {}
+}
+}
+}
+enum Plain {
+A,
+}
+impl Plain {
+fn name(&self) -> u8 {
+match self {
+Plain::A => 1,
+}
+}
+}
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+        let buffer = source_code.as_bytes().to_vec();
+
+        let enums = p.parse_enums(root_node, &buffer);
+        let machines = p.parse_state_machines(root_node, &buffer, &enums);
+        let light = machines.get(0).unwrap();
+        assert!(light.name == "Light");
+        assert!(light.states == vec!["Red", "Green", "Blinking"]);
+
+        let t = &light.transitions;
+        assert!(t.get(0).unwrap().from == "Red");
+        assert!(t.get(0).unwrap().to == "Green");
+        assert!(t.get(0).unwrap().label == "next / Light::Red");
+        assert!(t.get(1).unwrap().from == "Green");
+        assert!(t.get(1).unwrap().to == "Red");
+        assert!(t.get(2).unwrap().to == "Blinking");
+        assert!(t.get(3).unwrap().from == "Blinking");
+        assert!(t.get(3).unwrap().to == "Blinking");
+        assert!(t.get(4).unwrap().from == "Blinking");
+        assert!(t.get(4).unwrap().to == "Red");
+        assert!(t.get(4).unwrap().label == "reset / Light::Blinking(_)");
+        assert!(t.get(5).is_none());
+
+        assert!(machines.get(1).is_none());
+    }
+
+    #[test]
+    fn test_rust_parse_nested_state_transitions() {
+        let mut p = RustParser::new();
+        let source_code = "
+enum State {
+Idle,
+Running,
+Done,
+}
+impl State {
+fn on(self, ev: Event) -> State {
+match self {
+State::Idle => match ev {
+Event::Go => State::Running,
+_ => State::Idle,
+},
+State::Running => {
+let next = match ev { Event::Stop => State::Done, _ => State::Idle };
+next
+}
+State::Done => State::Done,
+}
+}
+}
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+        let buffer = source_code.as_bytes().to_vec();
+
+        let enums = p.parse_enums(root_node, &buffer);
+        let machines = p.parse_state_machines(root_node, &buffer, &enums);
+        let transitions: Vec<(&str, &str, &str)> = machines[0]
+            .transitions
+            .iter()
+            .map(|x| (x.from.as_str(), x.to.as_str(), x.label.as_str()))
+            .collect();
+        // the arms of a match giving the arm value, not of one bound to a variable
+        assert!(
+            transitions
+                == vec![
+                    ("Idle", "Running", "on / State::Idle"),
+                    ("Idle", "Idle", "on / State::Idle"),
+                    ("Done", "Done", "on / State::Done"),
+                ]
+        );
+    }
+
+    #[test]
+    fn test_rust_parse_activity() {
+        use crate::generator::plantuml::PlantUml;
+        let mut p = RustParser::new();
+        let source_code = "
+fn run() {
+loop {
+tick();
+}
+}
+impl Worker {
+pub fn run(&mut self) -> Result<(), Error> {
+let job = self.queue.pop()?;
+if job.is_empty() {
+return Ok(());
+} else {
+self.count += 1;
+}
+match job.kind {
+Kind::A => self.a(),
+_ => {}
+}
+for x in job.items {
+self.handle(x);
+}
+Ok(())
+}
+}
+";
+        let buffer = source_code.as_bytes().to_vec();
+
+        let activity = p.parse_activity(&buffer, "Worker::run").remove(0);
+        assert!(activity.name == "Worker::run");
+        let a = &activity.activities;
+        assert!(
+            matches!(a.get(0).unwrap(), UmlActivity::Try(x) if x == "let job = self.queue.pop()?")
+        );
+        match a.get(1).unwrap() {
+            UmlActivity::Decision {
+                condition,
+                then,
+                otherwise,
+            } => {
+                assert!(condition == "job.is_empty()");
+                assert!(
+                    matches!(then.get(0).unwrap(), UmlActivity::Return(x) if x == "return Ok(())")
+                );
+                assert!(
+                    matches!(otherwise.get(0).unwrap(), UmlActivity::Action(x) if x == "self.count += 1")
+                );
+            }
+            _ => panic!("no decision"),
+        }
+        match a.get(2).unwrap() {
+            UmlActivity::Switch { subject, cases } => {
+                assert!(subject == "job.kind");
+                assert!(cases.get(0).unwrap().pattern == "Kind::A");
+                assert!(cases.get(1).unwrap().pattern == "_");
+                assert!(cases.get(1).unwrap().activities.is_empty());
+                assert!(cases.get(2).is_none());
+            }
+            _ => panic!("no switch"),
+        }
+        assert!(
+            matches!(a.get(3).unwrap(), UmlActivity::Loop { condition, .. } if condition == "for x in job.items")
+        );
+        assert!(matches!(a.get(4).unwrap(), UmlActivity::Action(x) if x == "Ok(())"));
+        assert!(a.get(5).is_none());
+
+        // a bare loop repeats while true
+        let activity = p.parse_activity(&buffer, "run").remove(0);
+        assert!(
+            matches!(activity.activities.get(0).unwrap(), UmlActivity::Repeat { condition, .. } if condition == "true")
+        );
+        assert!(activity.to_plantuml().contains("repeat while (true)"));
+        assert!(p.parse_activity(&buffer, "Other::run").is_empty());
+    }
+
+    #[test]
+    fn test_rust_parse_calls() {
+        let mut p = RustParser::new();
+        let source_code = "
+struct Worker {
+storage: Arc<Mutex<Box<dyn Storage>>>,
+}
+impl Worker {
+pub fn run(&mut self, log: &Logger) {
+self.prepare();
+self.storage.save(1);
+log.info(\"saved\");
+Self::helper();
+Config::load();
+free_function();
+log.warn(self.format(Config::load()));
+}
+}
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+
+        let classes = p.parse_classes(root_node, &source_code.as_bytes().to_vec());
+        let calls = &classes.get(0).unwrap().methods.get(0).unwrap().calls;
+        let resolved: Vec<(Option<&str>, &str)> = calls
+            .iter()
+            .map(|x| (x.receiver.as_deref(), x.method.as_str()))
+            .collect();
+        assert!(
+            resolved
+                == vec![
+                    (Some("Worker"), "prepare"),
+                    (Some("Storage"), "save"),
+                    (Some("Logger"), "info"),
+                    (Some("Worker"), "helper"),
+                    (Some("Config"), "load"),
+                    (None, "free_function"),
+                    (Some("Config"), "load"),
+                    (Some("Worker"), "format"),
+                    (Some("Logger"), "warn"),
+                ]
+        );
+        assert!(calls.get(5).unwrap().text == "free_function()");
+    }
+
+    #[test]
+    fn test_rust_parse_body_uses() {
+        let mut p = RustParser::new();
+        let source_code = "
+struct X {
+}
+impl X {
+pub fn func1(&self) -> u8 {
+let foo = Foo::new();
+let v = Vec::new();
+crate::util::Bar::helper();
+std::mem::swap(&mut a, &mut b);
+match self.state {
+Baz::Variant => Self::other(),
+}
+Point { x: 1 };
+}
+}
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let root_node = tree.root_node();
+        let buffer = source_code.as_bytes().to_vec();
+
+        let classes = p.parse_classes(root_node, &buffer);
+        assert!(classes.get(0).unwrap().uses.is_empty());
+
+        p.set_scan_bodies(true);
+        let classes = p.parse_classes(root_node, &buffer);
+        assert!(classes.get(0).unwrap().uses == vec!["Foo", "Bar", "Baz", "Point"]);
+        assert!(classes.get(0).unwrap().dependencies.is_empty());
+    }
+    #[test]
+    fn test_rust_parse_enum_body_uses() {
+        use crate::generator::plantuml::PlantUml;
+        let mut p = RustParser::new();
+        let source_code = "
+enum Command {
+Quit,
+Say(String),
+}
+impl Command {
+fn run(&self) {
+match self {
+Command::Quit => Exit::now(),
+Self::Say(text) => Console { out: text },
+}
+}
+}
+";
+        let buffer = source_code.as_bytes().to_vec();
+        p.set_scan_bodies(true);
+        p.parse(&buffer);
+
+        assert!(p.enums()[0].uses == vec!["Exit", "Console"]);
+        assert!(p.enums()[0]
+            .to_plantuml()
+            .contains("Command ..> Exit : <<uses>>"));
+    }
+    #[test]
+    fn test_rust_parse_module_dependencies() {
+        let mut p = RustParser::new();
+        let source_code = "
+use crate::uml::{UmlClass, UmlEnum};
+use super::db::{self, Storage as Store};
+use std::collections::HashMap;
+use self::inner::*;
+use crate::parser;
+mod inner;
+mod util {
+fn pad() {}
+}
+fn run() {
+crate::uml::UmlClass::new();
+Store::open();
+self::util::pad();
+crate::parser::module_path(path);
+super::super::config::load();
+parser::one_line(node);
+db::open();
+helper();
+}
+";
+        for module in &[
+            "crate::uml",
+            "crate::sync::db",
+            "crate::parser",
+            "crate::config",
+        ] {
+            p.parse_module(module, &vec![]);
+        }
+        let buffer = source_code.as_bytes().to_vec();
+        p.parse_module("crate::sync::worker", &buffer);
+        p.finish();
+        let module = p.modules().get(4).unwrap();
+        assert!(module.name == "crate::sync::worker");
+        let dependencies: Vec<(&str, usize)> = module
+            .dependencies
+            .iter()
+            .map(|x| (x.to.as_str(), x.weight))
+            .collect();
+        assert!(
+            dependencies
+                == vec![
+                    ("crate::uml", 2),
+                    ("crate::sync::db", 3),
+                    ("crate::sync::worker::inner", 1),
+                    ("crate::parser", 3),
+                    ("crate::sync::worker::util", 1),
+                    ("crate::config", 1),
+                ]
+        );
+        let names: Vec<&str> = p.modules().iter().map(|x| x.name.as_str()).collect();
+        assert!(names[5..] == ["crate::sync::worker::inner", "crate::sync::worker::util"]);
+    }
+    #[test]
+    fn test_rust_parse_feature_gates() {
+        let mut p = RustParser::new();
+        let source_code = "
+#[cfg(feature = \"serde\")]
+#[derive(Serialize)]
+struct X {
+#[cfg(all(feature = \"db\", not(feature = \"wasm\")))]
+pool: Pool,
+}
+impl X {
+#[cfg(feature=\"db\")]
+// opens the pool
+pub fn open() {}
+}
+#[cfg(not(feature = \"std\"))]
+fn alloc() {}
+#[cfg(test)]
+mod tests {}
+";
+        p.module = "crate::model".to_owned();
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let buffer = source_code.as_bytes().to_vec();
+        let gates = p.parse_feature_gates(tree.root_node(), &buffer);
+        let gates: Vec<(&str, &str)> = gates
+            .iter()
+            .map(|x| (x.feature.as_str(), x.item.as_str()))
+            .collect();
+        assert!(
+            gates
+                == vec![
+                    ("serde", "crate::model::X"),
+                    ("db", "crate::model::X::pool"),
+                    ("db", "crate::model::X::open"),
+                ]
+        );
+    }
+    #[test]
+    fn test_rust_parse_schema() {
+        let mut p = RustParser::new();
+        let source_code = "
+diesel::table! {
+    use diesel::sql_types::*;
+
+    users (id) {
+        id -> Int4,
+        #[sql_name = \"type\"]
+        type_ -> Varchar,
+        email -> Nullable<Text>,
+    }
+
+    posts {
+        id -> Int4,
+        user_id -> Int4,
+    }
+}
+joinable!(posts -> users (user_id));
+allow_tables_to_appear_in_same_query!(posts, users);
+
+#[derive(Queryable, diesel::Insertable)]
+#[diesel(table_name = crate::schema::users)]
+pub struct User {
+    pub id: i32,
+}
+#[derive(Debug, Queryable)]
+pub struct BlogPost {
+    pub id: i32,
+}
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = \"comments\")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub body: String,
+}
+#[derive(sqlx::FromRow)]
+struct AccountRow {
+    id: i64,
+}
+fn load(pool: &PgPool) {
+    sqlx::query_as!(AccountRow, \"SELECT id FROM accounts WHERE id = $1\", 1);
+    sqlx::query_as::<_, Session>(r#\"SELECT * FROM \"sessions\"\"#);
+}
+";
+        let buffer = source_code.as_bytes().to_vec();
+        p.parse(&buffer);
+        p.finish();
+        let schema = p.schema();
+
+        let entities: Vec<&str> = schema.entities.iter().map(|x| x.name.as_str()).collect();
+        assert!(entities == vec!["users", "posts", "comments"]);
+        let columns: Vec<(&str, &str, bool)> = schema.entities[0]
+            .columns
+            .iter()
+            .map(|x| (x.name.as_str(), x.data_type.as_str(), x.primary_key))
+            .collect();
+        assert!(
+            columns
+                == vec![
+                    ("id", "Int4", true),
+                    ("type_", "Varchar", false),
+                    ("email", "Nullable<Text>", false)
+                ]
+        );
+        assert!(schema.entities[1].columns[0].primary_key);
+        assert!(schema.entities[2].columns[0].primary_key);
+        assert!(!schema.entities[2].columns[1].primary_key);
+
+        let join = schema.joins.get(0).unwrap();
+        assert!(join.from == "posts" && join.to == "users" && join.column == "user_id");
+
+        let mappings: Vec<(&str, &str)> = schema
+            .mappings
+            .iter()
+            .map(|x| (x.type_name.as_str(), x.table.as_str()))
+            .collect();
+        assert!(
+            mappings
+                == vec![
+                    ("User", "users"),
+                    ("BlogPost", "blog_posts"),
+                    ("Model", "comments"),
+                    ("AccountRow", "accounts"),
+                    ("Session", "sessions"),
+                ]
+        );
+        assert!(schema.query_groups == vec![vec!["posts", "users"]]);
+        assert!(schema.mappings[0].derives == vec!["Queryable", "Insertable"]);
+    }
+    #[test]
+    fn test_rust_parse_macros() {
+        let mut p = RustParser::new();
+        let source_code = "
+macro_rules! point {
+    () => { Point::default() };
+    ($x:expr, $y:expr) => { Point::new($x, $y) };
+}
+lazy_static! {
+    static ref ORIGIN: Point = point!();
+}
+mod ops {
+    impl Point {
+        forward_ops!(Add, add);
+    }
+}
+fn main() {
+    println!(\"{:?}\", point!(1, 2));
+}
+";
+        p.module = "crate::geometry".to_owned();
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let buffer = source_code.as_bytes().to_vec();
+        let inventory = p.parse_macros(tree.root_node(), &buffer);
+
+        let m = inventory.macros.get(0).unwrap();
+        assert!(m.name == "point" && m.module == "crate::geometry");
+        assert!(m.rules == vec!["()", "($x:expr, $y:expr)"]);
+
+        let invocations: Vec<(&str, &str, Option<&str>)> = inventory
+            .invocations
+            .iter()
+            .map(|x| (x.name.as_str(), x.module.as_str(), x.owner.as_deref()))
+            .collect();
+        assert!(
+            invocations
+                == vec![
+                    ("lazy_static", "crate::geometry", None),
+                    ("forward_ops", "crate::geometry::ops", Some("Point")),
+                ]
+        );
+    }
+    #[test]
+    fn test_rust_parse_ffi() {
+        let mut p = RustParser::new();
+        let source_code = "
+#[repr(C)]
+pub struct Config {
+    pub size: u32,
+}
+#[repr(C, u8)]
+pub enum Event {
+    Open,
+}
+#[repr(transparent)]
+pub struct Handle(u64);
+extern \"C\" {
+    fn c_init(cfg: *const Config) -> i32;
+}
+extern {
+    fn c_free(p: *mut c_void);
+}
+#[no_mangle]
+pub extern \"C\" fn rs_open(size: u32) -> *mut Config {}
+#[no_mangle]
+pub fn rs_version() -> u32 {}
+pub extern \"C\" fn callback(x: u32) {}
+";
+        let tree = p.ts_parser.parse(source_code, None).unwrap();
+        let buffer = source_code.as_bytes().to_vec();
+        let ffi = p.parse_ffi(tree.root_node(), &buffer);
+
+        let imports: Vec<(&str, &str)> = ffi
+            .imports
+            .iter()
+            .map(|x| (x.name.as_str(), x.abi.as_str()))
+            .collect();
+        assert!(imports == vec![("c_init", "extern \"C\""), ("c_free", "extern \"C\"")]);
+        let c_init = ffi.imports.get(0).unwrap();
+        assert!(c_init.parameters.get(0).unwrap().data_type == "*const Config");
+        assert!(c_init.return_type.as_deref() == Some("i32"));
+
+        let exports: Vec<(&str, &str)> = ffi
+            .exports
+            .iter()
+            .map(|x| (x.name.as_str(), x.abi.as_str()))
+            .collect();
+        assert!(exports == vec![("rs_open", "extern \"C\""), ("rs_version", "Rust")]);
+        assert!(ffi.types == vec!["Config", "Event"]);
+
+        // the C side of the layout of `Config`
+        use crate::generator::plantuml::{FfiDiagram, PlantUml};
+        let mut c = crate::parser::cpp_parser::CppParser::new();
+        let header = "struct Config { unsigned int size; };\nint c_init(const struct Config *cfg);";
+        c.parse_module("api.h", &header.as_bytes().to_vec());
+        c.finish();
+        let diagram = FfiDiagram {
+            boundary: &ffi,
+            c_functions: c.functions(),
+            c_types: c.classes(),
+        }
+        .to_plantuml();
+        assert!(diagram.contains("c_init <<C>>"));
+        assert!(diagram.contains("Config .. c_type_Config : same layout"));
+    }
 }
@@ -0,0 +1,239 @@
+use crate::uml::*;
+
+/// Splits macro input into identifiers, string literals, `->` and single punctuation
+/// characters, dropping whitespace and comments.
+pub fn tokens(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut v = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        match (chars[i], chars.get(i + 1)) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('/', Some('/')) => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            ('/', Some('*')) => {
+                while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+                    i += 1;
+                }
+                i += 2;
+                continue;
+            }
+            ('-', Some('>')) => i += 2,
+            ('"', _) => {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+                i += 1;
+            }
+            (c, _) if c.is_alphanumeric() || c == '_' => {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+            }
+            _ => i += 1,
+        }
+        v.push(chars[start..i.min(chars.len())].iter().collect());
+    }
+    v
+}
+
+/// Index after the group opened at `tokens[i]`, e.g. after the `]` of `[...]`.
+fn skip_group(tokens: &[String], i: usize) -> usize {
+    let mut depth = 0;
+    for (j, token) in tokens.iter().enumerate().skip(i) {
+        match token.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return j + 1;
+        }
+    }
+    tokens.len()
+}
+
+/// The tokens inside of the delimiters of a macro invocation.
+fn inner(tokens: &[String]) -> &[String] {
+    match tokens.first().map(|x| x.as_str()) {
+        Some("(") | Some("[") | Some("{") if tokens.len() > 1 => &tokens[1..tokens.len() - 1],
+        _ => tokens,
+    }
+}
+
+/// Tables declared by a `table!` invocation, `{ users (id) { id -> Int4, name -> Text, } }`.
+/// Tables without a primary key list use `id`.
+pub fn parse_table_macro(text: &str) -> Vec<UmlEntity> {
+    let tokens = tokens(text);
+    let tokens = inner(&tokens);
+    let mut entities = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i].as_str() {
+            "use" => {
+                while i < tokens.len() && tokens[i] != ";" {
+                    i += 1;
+                }
+                i += 1;
+            }
+            "#" => i = skip_group(tokens, i + 1),
+            _ => {
+                // `schema.users` names the table `users`
+                let mut name = &tokens[i];
+                i += 1;
+                while i + 1 < tokens.len() && tokens[i] == "." {
+                    name = &tokens[i + 1];
+                    i += 2;
+                }
+                let mut primary_keys: Vec<&str> = vec!["id"];
+                if tokens.get(i).map(|x| x.as_str()) == Some("(") {
+                    let end = skip_group(tokens, i);
+                    primary_keys = tokens[i + 1..end - 1]
+                        .iter()
+                        .map(|x| x.as_str())
+                        .filter(|x| *x != ",")
+                        .collect();
+                    i = end;
+                }
+                if tokens.get(i).map(|x| x.as_str()) != Some("{") {
+                    break;
+                }
+                let end = skip_group(tokens, i);
+                entities.push(UmlEntity {
+                    name: name.to_owned(),
+                    columns: parse_columns(&tokens[i + 1..end - 1], &primary_keys),
+                });
+                i = end;
+            }
+        }
+    }
+    entities
+}
+
+/// Columns like `email -> Nullable<Text>,` with their `#[sql_name = ".."]` attributes skipped.
+fn parse_columns(tokens: &[String], primary_keys: &[&str]) -> Vec<UmlColumn> {
+    let mut columns = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i].as_str() {
+            "#" => i = skip_group(tokens, i + 1),
+            "," => i += 1,
+            name => {
+                if tokens.get(i + 1).map(|x| x.as_str()) != Some("->") {
+                    break;
+                }
+                let mut j = i + 2;
+                let mut depth = 0;
+                while j < tokens.len() && !(depth == 0 && tokens[j] == ",") {
+                    match tokens[j].as_str() {
+                        "<" => depth += 1,
+                        ">" => depth -= 1,
+                        _ => {}
+                    }
+                    j += 1;
+                }
+                columns.push(UmlColumn {
+                    name: name.to_owned(),
+                    data_type: tokens[i + 2..j].concat(),
+                    primary_key: primary_keys.contains(&name),
+                });
+                i = j;
+            }
+        }
+    }
+    columns
+}
+
+/// The foreign key of a `joinable!` invocation, `(posts -> users (user_id))`.
+pub fn parse_joinable_macro(text: &str) -> Option<UmlJoin> {
+    let tokens = tokens(text);
+    match inner(&tokens) {
+        [from, arrow, to, open, column, ..] if arrow == "->" && open == "(" => Some(UmlJoin {
+            from: from.to_owned(),
+            to: to.to_owned(),
+            column: column.to_owned(),
+        }),
+        _ => None,
+    }
+}
+
+/// Tables of an `allow_tables_to_appear_in_same_query!(posts, users)` invocation.
+pub fn parse_query_group_macro(text: &str) -> Vec<String> {
+    let tokens = tokens(text);
+    inner(&tokens)
+        .iter()
+        .filter(|x| x.as_str() != ",")
+        .map(|x| x.to_owned())
+        .collect()
+}
+
+/// The row type and table of `query_as!(User, "SELECT * FROM users WHERE id = $1", id)`.
+pub fn parse_query_as_macro(text: &str) -> Option<(String, String)> {
+    let tokens = tokens(text);
+    let tokens = inner(&tokens);
+    let comma = tokens.iter().position(|x| x == ",")?;
+    // `models::User` is the row type `User`
+    let row = tokens[..comma].last()?;
+    let query = tokens[comma..].iter().find(|x| x.starts_with('"'))?;
+    Some((row.to_owned(), query_table(query)?))
+}
+
+/// The table a query reads, the first one after `FROM`, `"SELECT * FROM users"` => `users`.
+/// Takes the literal as written, raw strings included.
+pub fn query_table(query: &str) -> Option<String> {
+    let query = query
+        .trim_start_matches('r')
+        .trim_matches('#')
+        .trim_matches('"');
+    let tokens = tokens(&query.replace("\\\"", "\""));
+    let i = tokens.iter().position(|x| x.eq_ignore_ascii_case("from"))?;
+    // `public.users` is the table `users`
+    let mut j = i + 1;
+    while j + 2 < tokens.len() && tokens[j + 1] == "." {
+        j += 2;
+    }
+    let table = tokens.get(j)?.trim_matches('"');
+    match table.starts_with(|x: char| x.is_alphabetic() || x == '_') {
+        true => Some(table.to_owned()),
+        false => None,
+    }
+}
+
+/// Table Diesel maps a struct to without a `table_name` attribute, `UserRole` => `user_roles`.
+pub fn default_table_name(type_name: &str) -> String {
+    let mut name = String::new();
+    for (i, c) in type_name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.extend(c.to_lowercase());
+    }
+    name.push('s');
+    name
+}
+
+/// The table of `#[diesel(table_name = users)]`, `#[table_name = "users"]` or
+/// `#[sea_orm(table_name = "users")]`.
+pub fn table_name_attribute(attribute: &str) -> Option<String> {
+    let tokens = tokens(attribute);
+    let i = tokens.iter().position(|x| x == "table_name")?;
+    match tokens.get(i + 1).map(|x| x.as_str()) {
+        Some("=") => {}
+        _ => return None,
+    }
+    // `schema::users` refers to the table `users`
+    let mut j = i + 2;
+    while j + 3 < tokens.len() && tokens[j + 1] == ":" && tokens[j + 2] == ":" {
+        j += 3;
+    }
+    tokens.get(j).map(|x| x.trim_matches('"').to_owned())
+}
@@ -6,6 +6,7 @@ pub enum UmlTopEntity {
 #[derive(Debug)]
 pub enum UmlVisibility {
     Private,
+    Protected,
     Public,
 }
 #[derive(Debug)]
@@ -13,10 +14,53 @@ pub enum UmlClassModifier {
     Abstract,
     Interface,
 }
+#[derive(Debug, PartialEq)]
+pub enum UmlMemberModifier {
+    /// Pure virtual, `= 0`.
+    Abstract,
+    Virtual,
+    Override,
+    Final,
+    Static,
+    /// A `const` member function, the cv-qualifiers of fields stay part of their type.
+    Const,
+    Constexpr,
+    Inline,
+    Explicit,
+    Noexcept,
+    /// `= default`
+    Default,
+    /// `= delete`
+    Delete,
+}
+#[derive(Debug, PartialEq)]
+pub enum UmlMethodKind {
+    Method,
+    Constructor,
+    Destructor,
+    /// An operator overload, `operator==`.
+    Operator,
+    /// A conversion operator, `operator bool`.
+    Conversion,
+}
 #[derive(Debug)]
 pub struct UmlEnum {
-    name: String,
-    variants: Vec<String>,
+    pub name: String,
+    pub variants: Vec<String>,
+    pub methods: Vec<UmlMethod>,
+    pub implements: Vec<String>,
+    /// Types used in method bodies, like the `uses` of a class.
+    pub uses: Vec<String>,
+    /// Explicit values of the variants, `RED = 1`, in the order of `variants`.
+    pub values: Vec<Option<String>>,
+    /// The underlying type of `enum class Mode : uint8_t`.
+    pub underlying_type: Option<String>,
+    /// An `enum class` or `enum struct`.
+    pub is_scoped: bool,
+    /// The class this enum is nested in.
+    pub owner: Option<String>,
+    /// Namespace the enum is declared in, `net::http`.
+    pub package: Option<String>,
 }
 #[derive(Debug)]
 pub struct UmlParameter {
@@ -28,19 +72,47 @@ pub struct UmlAssociation {
     pub to: String,
     pub from_title: String,
     pub to_title: String,
+    pub polymorphic: bool,
+    /// Held by a static member, shared by all instances.
+    pub is_static: bool,
+}
+#[derive(Debug)]
+pub struct UmlDependency {
+    pub to: String,
+    pub polymorphic: bool,
+}
+#[derive(Debug)]
+pub struct UmlCall {
+    /// Type the call dispatches to, `None` if it could not be resolved.
+    pub receiver: Option<String>,
+    pub method: String,
+    pub text: String,
 }
 #[derive(Debug)]
 pub struct UmlMethod {
     pub name: String,
+    pub kind: UmlMethodKind,
     pub visibility: UmlVisibility,
     pub parameters: Vec<UmlParameter>,
     pub return_type: Option<String>,
+    pub calls: Vec<UmlCall>,
+    /// Parameters of a member function template, `U` of `template <typename U> void push(U)`.
+    pub template_parameters: Vec<String>,
+    pub modifiers: Vec<UmlMemberModifier>,
+}
+/// A base class, `protected virtual io::Stream`.
+#[derive(Debug)]
+pub struct UmlGeneralization {
+    pub name: String,
+    pub access: UmlVisibility,
+    pub is_virtual: bool,
 }
 #[derive(Debug)]
 pub struct UmlField {
     pub name: String,
     pub data_type: String,
     pub visibility: UmlVisibility,
+    pub modifiers: Vec<UmlMemberModifier>,
 }
 #[derive(Debug)]
 pub struct UmlInterface {
@@ -56,12 +128,176 @@ pub struct UmlClass {
     pub visibility: UmlVisibility,
     pub methods: Vec<UmlMethod>,
     pub fields: Vec<UmlField>,
-    pub extends: Vec<String>,
+    pub extends: Vec<UmlGeneralization>,
     pub implements: Vec<String>,
     pub associations: Vec<UmlAssociation>,
+    pub dependencies: Vec<UmlDependency>,
+    /// Types used in method bodies, apart from the signature level `dependencies`.
+    pub uses: Vec<String>,
+    /// The class this class is nested in.
+    pub owner: Option<String>,
+    /// Namespace the class is declared in, `net::http`.
+    pub package: Option<String>,
+    /// Parameters of a class template, `T` and `int N` of `template <typename T, int N>`.
+    pub template_parameters: Vec<String>,
+    /// The primary template of a specialization, `Buffer` for `Buffer<bool>`.
+    pub specializes: Option<String>,
 }
 #[derive(Debug)]
 pub struct UmlStruct {
     name: String,
     visibility: UmlVisibility,
 }
+#[derive(Debug)]
+pub enum UmlActivity {
+    Action(String),
+    Decision {
+        condition: String,
+        then: Vec<UmlActivity>,
+        otherwise: Vec<UmlActivity>,
+    },
+    Switch {
+        subject: String,
+        cases: Vec<UmlActivityCase>,
+    },
+    Loop {
+        condition: String,
+        body: Vec<UmlActivity>,
+    },
+    /// A loop checking its condition after the body, `do { ... } while (...)`.
+    Repeat {
+        condition: String,
+        body: Vec<UmlActivity>,
+    },
+    /// A statement which returns early on error, e.g. through `?`.
+    Try(String),
+    Return(String),
+}
+#[derive(Debug)]
+pub struct UmlActivityCase {
+    pub pattern: String,
+    pub activities: Vec<UmlActivity>,
+}
+#[derive(Debug)]
+pub struct UmlActivityDiagram {
+    pub name: String,
+    pub activities: Vec<UmlActivity>,
+}
+#[derive(Debug)]
+pub struct UmlTransition {
+    pub from: String,
+    pub to: String,
+    pub label: String,
+}
+#[derive(Debug)]
+pub struct UmlStateMachine {
+    pub name: String,
+    pub states: Vec<String>,
+    pub transitions: Vec<UmlTransition>,
+}
+#[derive(Debug)]
+pub struct UmlBlanketImpl {
+    pub trait_name: String,
+    pub target: String,
+    pub bounds: Vec<String>,
+}
+#[derive(Debug)]
+pub struct UmlConversion {
+    pub from: String,
+    pub to: String,
+    pub kind: String,
+}
+#[derive(Debug)]
+pub struct UmlConversionGraph {
+    pub conversions: Vec<UmlConversion>,
+    pub error_types: Vec<String>,
+}
+#[derive(Debug)]
+pub struct UmlModuleDependency {
+    pub to: String,
+    /// Number of items referenced through this dependency.
+    pub weight: usize,
+}
+#[derive(Debug)]
+pub struct UmlModule {
+    pub name: String,
+    pub dependencies: Vec<UmlModuleDependency>,
+}
+/// An item compiled only with a cargo feature, through `#[cfg(feature = "...")]`.
+#[derive(Debug)]
+pub struct UmlFeatureGate {
+    pub feature: String,
+    pub item: String,
+}
+#[derive(Debug)]
+pub struct UmlColumn {
+    pub name: String,
+    pub data_type: String,
+    pub primary_key: bool,
+}
+/// A database table.
+#[derive(Debug)]
+pub struct UmlEntity {
+    pub name: String,
+    pub columns: Vec<UmlColumn>,
+}
+/// A foreign key of `from` referencing `to`.
+#[derive(Debug)]
+pub struct UmlJoin {
+    pub from: String,
+    pub to: String,
+    pub column: String,
+}
+/// A struct read from or written to a table, e.g. through `#[derive(Queryable)]`.
+#[derive(Debug)]
+pub struct UmlTableMapping {
+    pub type_name: String,
+    pub table: String,
+    pub derives: Vec<String>,
+}
+#[derive(Debug)]
+pub struct UmlSchema {
+    pub entities: Vec<UmlEntity>,
+    pub joins: Vec<UmlJoin>,
+    pub mappings: Vec<UmlTableMapping>,
+    /// Tables of `allow_tables_to_appear_in_same_query!`, which may be joined in a query.
+    pub query_groups: Vec<Vec<String>>,
+}
+/// A `macro_rules!` definition with the matchers of its rules.
+#[derive(Debug)]
+pub struct UmlMacro {
+    pub name: String,
+    pub module: String,
+    pub rules: Vec<String>,
+}
+/// An item-level macro invocation, whose generated items are not modelled.
+#[derive(Debug)]
+pub struct UmlMacroInvocation {
+    pub name: String,
+    pub module: String,
+    /// The type of the impl block the invocation is placed in.
+    pub owner: Option<String>,
+}
+#[derive(Debug)]
+pub struct UmlMacroInventory {
+    pub macros: Vec<UmlMacro>,
+    pub invocations: Vec<UmlMacroInvocation>,
+}
+/// A function crossing the language boundary.
+#[derive(Debug)]
+pub struct UmlFfiFunction {
+    pub name: String,
+    /// `extern "C"` for Rust functions, `C` or `C++` for declarations seen by the C++ parser.
+    pub abi: String,
+    pub parameters: Vec<UmlParameter>,
+    pub return_type: Option<String>,
+}
+#[derive(Debug)]
+pub struct UmlFfiBoundary {
+    /// Functions declared in `extern` blocks.
+    pub imports: Vec<UmlFfiFunction>,
+    /// `#[no_mangle]` functions.
+    pub exports: Vec<UmlFfiFunction>,
+    /// `#[repr(C)]` structs, enums and unions.
+    pub types: Vec<String>,
+}